@@ -56,6 +56,8 @@ fn main() -> Result<(), Box<dyn Error>> {
                 output_config.channels() as usize,
                 DecoderSettings {
                     enable_gapless: true,
+                    trim_trailing_silence: true,
+                    ..Default::default()
                 },
             )?;
             if let Some(seek_position) = seek_position.take() {
@@ -188,6 +188,8 @@ fn event_loop<B: AudioBackend>(
             let source = Box::new(ReadSeekSource::from_path(Path::new(&current_file)));
             let mut decoder = manager.init_decoder(source, DecoderSettings {
                 enable_gapless: true,
+                trim_trailing_silence: true,
+                ..Default::default()
             })?;
             if let Some(seek_position) = seek_position.take() {
                 decoder.seek(seek_position).unwrap();
@@ -213,17 +215,18 @@ fn event_loop<B: AudioBackend>(
                             decoder.resume();
                         }
                         Command::Next => {
+                            manager.fade_out(&mut decoder).ok();
                             break true;
                         }
                         Command::Volume(volume) => {
                             manager.set_volume(volume);
-                            decoder.set_volume(volume);
                         }
                         Command::Stop => {
+                            manager.fade_out(&mut decoder).ok();
                             return Ok(());
                         }
                         Command::Seek(time) => {
-                            decoder.seek(time).unwrap();
+                            manager.seek(&mut decoder, time).unwrap();
                         }
                         Command::Reset => {
                             reset = true;
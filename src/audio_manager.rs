@@ -1,11 +1,18 @@
-use cpal::{SampleRate, SizedSample, SupportedStreamConfig};
+use std::time::{Duration, Instant};
+
+use cpal::{ChannelCount, SampleRate, SizedSample, SupportedStreamConfig};
 use dasp::sample::Sample as DaspSample;
 use symphonia::core::audio::conv::ConvertibleSample;
 use symphonia::core::audio::sample::Sample;
 
+use crate::crossfade::{Crossfade, CrossfadeSettings};
 use crate::decoder::{
-    Decoder, DecoderError, DecoderResult, DecoderSettings, ResampledDecoder, ResamplerSettings,
-    Source,
+    DEFAULT_VOLUME_RAMP, Decoder, DecoderError, DecoderResult, DecoderSettings, ResampledDecoder,
+    ResamplerSettings, SeekError, Source, VolumeCurve, VolumeRamp, db_to_linear, linear_to_db,
+};
+use crate::effects::{
+    ChannelControls, Crossfeed, CrossfeedSettings, Dither, DitherSettings, Effect, EffectChain,
+    Equalizer, Limiter, LimiterSettings, TimeStretch, VocalRemover,
 };
 use crate::output::{
     AudioBackend, AudioOutput, AudioOutputError, OutputBuilder, RequestedOutputConfig,
@@ -31,18 +38,45 @@ pub enum ResetError {
     WriteBlockingError(#[from] WriteBlockingError),
     #[error(transparent)]
     DecoderError(#[from] DecoderError),
+    #[error("Error filling the output buffer: {0:?}")]
+    PrefillError(rb::RbError),
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum SelectTrackError {
+    #[error(transparent)]
+    DecoderError(#[from] DecoderError),
+    #[error(transparent)]
+    WriteBlockingError(#[from] WriteBlockingError),
+}
+
+/// Plays decoders through an output device.
+///
+/// Decoders always produce `f32` samples so effects, volume and crossfades are applied without
+/// losing precision. `T` is only the format written to the output, and the conversion to it is
+/// dithered when it's a 16-bit or smaller integer format. Earlier versions decoded straight to
+/// `T`, so decoders passed to the manager are now `Decoder<f32>` whatever `T` is.
 pub struct AudioManager<T: Sample + DaspSample, B: AudioBackend> {
     output_builder: OutputBuilder<B>,
     output_config: SupportedStreamConfig,
     output: AudioOutput<T, B>,
-    resampled: ResampledDecoder<T>,
+    resampled: ResampledDecoder<f32>,
     device_name: Option<String>,
+    output_channels: Option<ChannelCount>,
     resampler_settings: ResamplerSettings,
-    volume: T::Float,
+    crossfade: Option<Crossfade<f32>>,
+    crossfade_settings: CrossfadeSettings,
+    time_stretch: TimeStretch,
+    effects: OutputEffects,
+    dither_settings: Option<DitherSettings>,
+    dither: Option<Dither>,
+    buf: Vec<f32>,
+    stretch_buf: Vec<f32>,
+    out_buf: Vec<T>,
 }
 
+const DEFAULT_FADE_DURATION: Duration = Duration::from_millis(20);
+
 impl<
     T: Sample + DaspSample + SizedSample + ConvertibleSample + rubato::Sample + Send,
     B: AudioBackend,
@@ -57,20 +91,37 @@ impl<
         let output: AudioOutput<T, B> =
             output_builder.new_output::<T>(None, output_config.clone())?;
 
-        let resampled = ResampledDecoder::<T>::new(
+        let resampled = ResampledDecoder::<f32>::new(
             output_config.sample_rate().0 as usize,
             output_config.channels() as usize,
             resampler_settings.clone(),
         );
 
+        let sample_rate = output_config.sample_rate().0 as usize;
+        let mut time_stretch = TimeStretch::new();
+        time_stretch.initialize(sample_rate, output_config.channels() as usize);
+        let mut effects = OutputEffects::new();
+        effects.initialize(sample_rate, output_config.channels() as usize);
+        let dither_settings = Some(DitherSettings::default());
+        let dither = create_dither::<T>(dither_settings.as_ref(), &output_config);
+
         Ok(Self {
             output_config,
             output_builder,
             output,
             resampled,
             device_name: None,
+            output_channels: None,
             resampler_settings,
-            volume: 1.0.to_sample(),
+            crossfade: None,
+            crossfade_settings: CrossfadeSettings::default(),
+            time_stretch,
+            effects,
+            dither_settings,
+            dither,
+            buf: Vec::new(),
+            stretch_buf: Vec::new(),
+            out_buf: Vec::new(),
         })
     }
 
@@ -86,40 +137,296 @@ impl<
         self.device_name = device;
     }
 
+    pub fn set_output_channels(&mut self, channels: Option<ChannelCount>) {
+        self.output_channels = channels;
+    }
+
+    /// Sets the master volume. This is applied to the output after any per-decoder volume and
+    /// changes gradually over the configured ramp time.
     pub fn set_volume(&mut self, volume: T::Float) {
-        self.volume = volume;
+        self.effects.volume.ramp.set_target(volume.to_sample());
+    }
+
+    pub fn volume(&self) -> T::Float {
+        self.effects.volume.ramp.target().to_sample()
+    }
+
+    pub fn set_volume_db(&mut self, volume_db: f32) {
+        self.effects.volume.ramp.set_target(db_to_linear(volume_db));
+    }
+
+    pub fn volume_db(&self) -> f32 {
+        linear_to_db(self.effects.volume.ramp.target())
+    }
+
+    /// Sets the master volume from a 0 to 1 scale using the given curve.
+    pub fn set_volume_scaled(&mut self, level: f32, curve: VolumeCurve) {
+        self.effects.volume.ramp.set_target(curve.to_linear(level));
+    }
+
+    pub fn volume_scaled(&self, curve: VolumeCurve) -> f32 {
+        curve.from_linear(self.effects.volume.ramp.target())
+    }
+
+    /// Silences the output. The previous volume is restored by [`AudioManager::unmute`].
+    pub fn mute(&mut self) {
+        self.effects.volume.ramp.set_muted(true);
+    }
+
+    pub fn unmute(&mut self) {
+        self.effects.volume.ramp.set_muted(false);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.effects.volume.ramp.is_muted()
+    }
+
+    /// Sets the balance between the left and right channels from -1 (left only) to 1 (right
+    /// only).
+    pub fn set_balance(&mut self, balance: f32) {
+        self.effects.channel_controls.set_balance(balance);
+    }
+
+    pub fn balance(&self) -> f32 {
+        self.effects.channel_controls.balance()
+    }
+
+    pub fn set_channels_swapped(&mut self, is_swapped: bool) {
+        self.effects
+            .channel_controls
+            .set_channels_swapped(is_swapped);
+    }
+
+    pub fn channels_swapped(&self) -> bool {
+        self.effects.channel_controls.channels_swapped()
+    }
+
+    pub fn set_polarity_inverted(&mut self, invert_left: bool, invert_right: bool) {
+        self.effects
+            .channel_controls
+            .set_polarity_inverted(invert_left, invert_right);
+    }
+
+    pub fn polarity_inverted(&self) -> (bool, bool) {
+        self.effects.channel_controls.polarity_inverted()
+    }
+
+    /// Plays the same mix of all channels on every speaker.
+    pub fn set_mono(&mut self, is_mono: bool) {
+        self.effects.channel_controls.set_mono(is_mono);
+    }
+
+    pub fn is_mono(&self) -> bool {
+        self.effects.channel_controls.is_mono()
+    }
+
+    /// Changes the tempo without changing the pitch. The speed is limited to between
+    /// [`MIN_PLAYBACK_SPEED`](crate::effects::MIN_PLAYBACK_SPEED) and
+    /// [`MAX_PLAYBACK_SPEED`](crate::effects::MAX_PLAYBACK_SPEED). [`Decoder::current_position`]
+    /// still reports the position in the media rather than how long it's been playing.
+    pub fn set_playback_speed(&mut self, speed: f32) {
+        self.time_stretch.set_speed(speed);
+    }
+
+    pub fn playback_speed(&self) -> f32 {
+        self.time_stretch.speed()
+    }
+
+    /// Sets the length of the fades used by [`AudioManager::fade_out`] and when starting the
+    /// next decoder.
+    pub fn set_fade_duration(&mut self, fade_duration: Duration) {
+        self.effects.fade.set_ramp_time(fade_duration);
+    }
+
+    pub fn set_volume_ramp(&mut self, ramp_time: Duration) {
+        self.effects.volume.set_ramp_time(ramp_time);
+    }
+
+    pub fn crossfade_settings(&self) -> &CrossfadeSettings {
+        &self.crossfade_settings
+    }
+
+    pub fn set_crossfade_settings(&mut self, settings: CrossfadeSettings) {
+        self.crossfade_settings = settings;
+    }
+
+    /// Starts mixing the outgoing decoder into the output while it fades out. Call
+    /// [`AudioManager::initialize`] with the incoming decoder afterwards, which is faded in over
+    /// the same duration.
+    pub fn start_crossfade(&mut self, outgoing: Decoder<f32>) {
+        let channels = self.output_config.channels() as usize;
+        // The outgoing decoder keeps its resampler since the incoming one may use a different
+        // sample rate
+        let resampled = std::mem::replace(
+            &mut self.resampled,
+            ResampledDecoder::new(
+                self.output_config.sample_rate().0 as usize,
+                channels,
+                self.resampler_settings.clone(),
+            ),
+        );
+        self.crossfade = Some(Crossfade::new(
+            outgoing,
+            resampled,
+            &self.crossfade_settings,
+            channels,
+        ));
+    }
+
+    pub fn is_crossfading(&self) -> bool {
+        self.crossfade.is_some()
+    }
+
+    /// The effects applied to the output. Effects can be added, removed or reordered at any time.
+    pub fn effects(&self) -> &EffectChain {
+        &self.effects.chain
+    }
+
+    pub fn effects_mut(&mut self) -> &mut EffectChain {
+        &mut self.effects.chain
+    }
+
+    /// The built-in equalizer, which is applied before the effect chain.
+    pub fn equalizer(&self) -> &Equalizer {
+        &self.effects.equalizer
+    }
+
+    pub fn equalizer_mut(&mut self) -> &mut Equalizer {
+        &mut self.effects.equalizer
+    }
+
+    /// Karaoke mode, which removes center-panned vocals from stereo sources. This is applied
+    /// before the other effects.
+    pub fn vocal_remover(&self) -> &VocalRemover {
+        &self.effects.vocal_remover
+    }
+
+    pub fn vocal_remover_mut(&mut self) -> &mut VocalRemover {
+        &mut self.effects.vocal_remover
+    }
+
+    /// Enables or disables headphone crossfeed. This only affects stereo output.
+    pub fn set_crossfeed(&mut self, settings: Option<CrossfeedSettings>) {
+        self.effects.crossfeed = settings.map(|settings| {
+            let mut crossfeed = Crossfeed::new(settings);
+            crossfeed.initialize(
+                self.output_config.sample_rate().0 as usize,
+                self.output_config.channels() as usize,
+            );
+            crossfeed
+        });
+    }
+
+    pub fn crossfeed(&self) -> Option<&Crossfeed> {
+        self.effects.crossfeed.as_ref()
+    }
+
+    pub fn crossfeed_mut(&mut self) -> Option<&mut Crossfeed> {
+        self.effects.crossfeed.as_mut()
+    }
+
+    /// Enables or disables the limiter, which is applied after the master volume to keep the
+    /// output from clipping.
+    pub fn set_limiter(&mut self, settings: Option<LimiterSettings>) {
+        self.effects.limiter = settings.map(|settings| {
+            let mut limiter = Limiter::new(settings);
+            limiter.initialize(
+                self.output_config.sample_rate().0 as usize,
+                self.output_config.channels() as usize,
+            );
+            limiter
+        });
+    }
+
+    pub fn limiter(&self) -> Option<&Limiter> {
+        self.effects.limiter.as_ref()
+    }
+
+    pub fn limiter_mut(&mut self) -> Option<&mut Limiter> {
+        self.effects.limiter.as_mut()
+    }
+
+    /// Enables or disables dithering when samples are converted to a 16-bit or smaller integer
+    /// format, either because of `T` or because that's the format the device uses. This is
+    /// enabled by default and has no effect with other formats.
+    pub fn set_dither(&mut self, settings: Option<DitherSettings>) {
+        self.dither = create_dither::<T>(settings.as_ref(), &self.output_config);
+        self.dither_settings = settings;
+    }
+
+    pub fn dither_settings(&self) -> Option<&DitherSettings> {
+        self.dither_settings.as_ref()
+    }
+
+    /// Seeks the decoder and clears the state of any effects so audio from the previous position
+    /// doesn't carry over.
+    pub fn seek(
+        &mut self,
+        decoder: &mut Decoder<f32>,
+        time: Duration,
+    ) -> Result<Duration, SeekError> {
+        let res = decoder.seek(time);
+        self.time_stretch.reset();
+        self.effects.reset();
+        if let Some(dither) = &mut self.dither {
+            dither.reset();
+        }
+        res
     }
 
     pub fn init_decoder(
         &self,
         source: Box<dyn Source>,
         decoder_settings: DecoderSettings,
-    ) -> Result<Decoder<T>, DecoderError> {
-        Decoder::<T>::new(
+    ) -> Result<Decoder<f32>, DecoderError> {
+        Decoder::<f32>::new(
             source,
-            self.volume,
+            1.0,
             self.output_config.channels() as usize,
             decoder_settings,
         )
     }
 
-    pub fn initialize(&mut self, decoder: &mut Decoder<T>) -> Result<(), WriteBlockingError> {
+    pub fn initialize(&mut self, decoder: &mut Decoder<f32>) -> Result<(), WriteBlockingError> {
+        // A new resampler is created for the decoder's sample rate, so play what the current one is
+        // still holding. The effects carry on into the new decoder without being flushed.
         let res = if decoder.sample_rate() != self.resampled.in_sample_rate() {
-            self.flush_output()
+            self.drain_resampler()
         } else {
             Ok(())
         };
         self.resampled.initialize(decoder);
+        self.set_source_channels(decoder);
+        self.fade_in();
         res
     }
 
-    pub fn reset(&mut self, decoder: &mut Decoder<T>) -> Result<(), ResetError> {
+    /// Switches the decoder to another track in the same file and updates the output for the new
+    /// track's sample rate and channels.
+    pub fn select_track(
+        &mut self,
+        decoder: &mut Decoder<f32>,
+        track_id: u32,
+    ) -> Result<(), SelectTrackError> {
+        if track_id == decoder.track_id() {
+            return Ok(());
+        }
+        decoder.select_track(track_id)?;
+        self.initialize(decoder)?;
+        Ok(())
+    }
+
+    pub fn reset(&mut self, decoder: &mut Decoder<f32>) -> Result<(), ResetError> {
+        self.crossfade = None;
         self.flush()?;
+        let channels = self
+            .output_channels
+            .unwrap_or(self.output_config.channels());
         self.output_config = self.output_builder.find_closest_config(
             self.device_name.as_deref(),
             RequestedOutputConfig {
                 sample_rate: Some(SampleRate(decoder.sample_rate() as u32)),
-                channels: Some(self.output_config.channels()),
+                channels: Some(channels),
                 sample_format: Some(<T as cpal::SizedSample>::FORMAT),
             },
         )?;
@@ -127,6 +434,16 @@ impl<
         self.output = self
             .output_builder
             .new_output(None, self.output_config.clone())?;
+        decoder.set_output_channels(self.output_config.channels() as usize);
+        let sample_rate = self.output_config.sample_rate().0 as usize;
+        self.time_stretch
+            .initialize(sample_rate, self.output_config.channels() as usize);
+        self.effects
+            .initialize(sample_rate, self.output_config.channels() as usize);
+        self.set_source_channels(decoder);
+        // The device might not use the requested format
+        self.dither = create_dither::<T>(self.dither_settings.as_ref(), &self.output_config);
+        self.fade_in();
 
         self.resampled = ResampledDecoder::new(
             self.output_config.sample_rate().0 as usize,
@@ -138,7 +455,20 @@ impl<
 
         // Pre-fill output buffer before starting the stream
         while self.resampled.current(decoder).len() <= self.output.buffer_space_available() {
-            self.output.write(self.resampled.current(decoder)).unwrap();
+            self.process_output(decoder);
+            // Slowing down playback produces more samples than the decoder did, so they might not
+            // all fit
+            let written = match self.output.write(&self.out_buf) {
+                Ok(written) => written,
+                Err(rb::RbError::Full) => 0,
+                Err(error) => return Err(ResetError::PrefillError(error)),
+            };
+            if written < self.out_buf.len() {
+                self.output.start()?;
+                self.output.write_blocking(&self.out_buf[written..])?;
+                self.resampled.decode_next_frame(decoder)?;
+                return Ok(());
+            }
             if self.resampled.decode_next_frame(decoder)? == DecoderResult::Finished {
                 break;
             }
@@ -149,14 +479,18 @@ impl<
     }
 
     pub fn flush(&mut self) -> Result<(), WriteBlockingError> {
-        let res = self.flush_output();
+        let res = self
+            .drain_resampler()
+            .and_then(|()| self.finish_crossfade())
+            .and_then(|()| self.flush_effects());
         std::thread::sleep(self.output.settings().buffer_duration);
         self.output.stop();
         res
     }
 
-    pub fn write(&mut self, decoder: &mut Decoder<T>) -> Result<DecoderResult, WriteOutputError> {
-        let write_result = self.output.write_blocking(self.resampled.current(decoder));
+    pub fn write(&mut self, decoder: &mut Decoder<f32>) -> Result<DecoderResult, WriteOutputError> {
+        self.process_output(decoder);
+        let write_result = self.output.write_blocking(&self.out_buf);
         let decoder_result = self.resampled.decode_next_frame(decoder)?;
         write_result.map_err(|error| WriteOutputError::WriteBlockingError {
             error,
@@ -165,7 +499,24 @@ impl<
         Ok(decoder_result)
     }
 
-    pub fn write_all(&mut self, decoder: &mut Decoder<T>) -> Result<(), WriteOutputError> {
+    /// Plays the decoder until it has faded out and waits for the fade to finish playing. Use this
+    /// before stopping or skipping to the next track to avoid clicks. The next decoder passed to
+    /// [`AudioManager::initialize`] or [`AudioManager::reset`] is faded back in.
+    pub fn fade_out(
+        &mut self,
+        decoder: &mut Decoder<f32>,
+    ) -> Result<DecoderResult, WriteOutputError> {
+        self.effects.fade.ramp.set_target(0.0);
+        loop {
+            let result = self.write(decoder)?;
+            if result == DecoderResult::Finished || !self.effects.fade.ramp.is_ramping() {
+                self.wait_for_output();
+                return Ok(result);
+            }
+        }
+    }
+
+    pub fn write_all(&mut self, decoder: &mut Decoder<f32>) -> Result<(), WriteOutputError> {
         loop {
             if self.write(decoder)? == DecoderResult::Finished {
                 self.flush()
@@ -178,7 +529,240 @@ impl<
         }
     }
 
-    fn flush_output(&mut self) -> Result<(), WriteBlockingError> {
-        self.output.write_blocking(self.resampled.flush())
+    fn drain_resampler(&mut self) -> Result<(), WriteBlockingError> {
+        let samples = self.resampled.flush();
+        self.buf.clear();
+        self.buf.extend_from_slice(samples);
+        self.mix_crossfade();
+        self.apply_processing(false);
+        self.output.write_blocking(&self.out_buf)
+    }
+
+    // Lets the outgoing track finish fading out when the incoming one ends before the crossfade
+    // does
+    fn finish_crossfade(&mut self) -> Result<(), WriteBlockingError> {
+        let Some(crossfade) = &self.crossfade else {
+            return Ok(());
+        };
+        let channels = self.output_config.channels() as usize;
+        self.buf.clear();
+        self.buf
+            .resize(crossfade.remaining_frames() * channels, 0.0);
+        self.mix_crossfade();
+        self.apply_processing(false);
+        self.output.write_blocking(&self.out_buf)
+    }
+
+    // Plays the audio that time stretching and the effects are still holding back, such as the
+    // limiter's lookahead. This is only done at the end of the stream.
+    fn flush_effects(&mut self) -> Result<(), WriteBlockingError> {
+        self.buf.clear();
+        self.apply_processing(true);
+        self.output.write_blocking(&self.out_buf)
+    }
+
+    // Copies the decoder's current output so effects and the master volume can be applied
+    // before it's written
+    fn process_output(&mut self, decoder: &Decoder<f32>) {
+        self.buf.clear();
+        self.buf.extend_from_slice(self.resampled.current(decoder));
+        self.mix_crossfade();
+        self.apply_processing(false);
+    }
+
+    fn mix_crossfade(&mut self) {
+        if let Some(crossfade) = &mut self.crossfade {
+            crossfade.mix(&mut self.buf);
+            if crossfade.is_complete() {
+                self.crossfade = None;
+            }
+        }
+    }
+
+    // Everything up to the conversion to the output format works with floats, so the volume can
+    // push samples past full scale before the limiter brings them back down and the audio is only
+    // quantized once
+    fn apply_processing(&mut self, is_flush: bool) {
+        if self.time_stretch.is_active() {
+            self.stretch_buf.clear();
+            self.time_stretch.process(&self.buf, &mut self.stretch_buf);
+            if is_flush {
+                self.time_stretch.flush(&mut self.stretch_buf);
+            }
+            std::mem::swap(&mut self.buf, &mut self.stretch_buf);
+        }
+        self.effects.process(&mut self.buf);
+        if is_flush {
+            self.effects.flush(&mut self.buf);
+        }
+        if let Some(dither) = &mut self.dither {
+            dither.process(&mut self.buf);
+        }
+        self.out_buf.clear();
+        self.out_buf.extend(
+            self.buf
+                .iter()
+                .map(|s| s.to_sample::<T::Float>().to_sample::<T>()),
+        );
+    }
+
+    // Waits until the audio that's been written has been played. This gives up once the buffered
+    // audio should have finished in case the output has stalled.
+    fn wait_for_output(&self) {
+        let samples_per_sec =
+            self.output_config.sample_rate().0 as f64 * self.output_config.channels() as f64;
+        let timeout = Duration::from_secs_f64(self.output.buffer_size() as f64 / samples_per_sec)
+            + self.output.settings().buffer_duration;
+        let start = Instant::now();
+        while self.output.buffer_size() > 0 && start.elapsed() < timeout {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    // Vocal removal and mono depend on which channels the decoder's audio ends up in
+    fn set_source_channels(&mut self, decoder: &Decoder<f32>) {
+        self.effects
+            .vocal_remover
+            .set_source_channels(decoder.input_layout().len());
+        self.effects
+            .channel_controls
+            .set_main_channels(&decoder.main_output_channels());
+    }
+
+    fn fade_in(&mut self) {
+        if self.effects.fade.ramp.target() < 1.0 {
+            self.effects.fade.ramp.set_target(1.0);
+        }
+    }
+}
+
+// A volume ramp that can be placed in the effect chain
+struct Gain {
+    ramp: VolumeRamp,
+    ramp_time: Duration,
+    sample_rate: usize,
+    channels: usize,
+}
+
+impl Gain {
+    fn new(ramp_time: Duration) -> Self {
+        Self {
+            ramp: VolumeRamp::new(1.0),
+            ramp_time,
+            sample_rate: 0,
+            channels: 0,
+        }
+    }
+
+    fn set_ramp_time(&mut self, ramp_time: Duration) {
+        self.ramp_time = ramp_time;
+        self.ramp.set_ramp_time(ramp_time, self.sample_rate);
+    }
+}
+
+impl Effect for Gain {
+    fn initialize(&mut self, sample_rate: usize, channels: usize) {
+        self.sample_rate = sample_rate;
+        self.channels = channels;
+        self.ramp.set_ramp_time(self.ramp_time, sample_rate);
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        self.ramp.apply(samples, self.channels);
+    }
+
+    fn reset(&mut self) {}
+}
+
+// The built-in effects and the user's effect chain. The volume is applied near the end so it can
+// push samples past full scale before the limiter brings them back down.
+struct OutputEffects {
+    vocal_remover: VocalRemover,
+    equalizer: Equalizer,
+    crossfeed: Option<Crossfeed>,
+    chain: EffectChain,
+    channel_controls: ChannelControls,
+    volume: Gain,
+    fade: Gain,
+    limiter: Option<Limiter>,
+}
+
+impl OutputEffects {
+    fn new() -> Self {
+        Self {
+            vocal_remover: VocalRemover::default(),
+            equalizer: Equalizer::default(),
+            crossfeed: None,
+            chain: EffectChain::new(),
+            channel_controls: ChannelControls::new(),
+            volume: Gain::new(DEFAULT_VOLUME_RAMP),
+            fade: Gain::new(DEFAULT_FADE_DURATION),
+            limiter: None,
+        }
+    }
+
+    // The effects in processing order
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut dyn Effect> {
+        let effects: [Option<&mut dyn Effect>; 8] = [
+            Some(&mut self.vocal_remover),
+            Some(&mut self.equalizer),
+            self.crossfeed.as_mut().map(|c| c as &mut dyn Effect),
+            Some(&mut self.chain),
+            Some(&mut self.channel_controls),
+            Some(&mut self.volume),
+            Some(&mut self.fade),
+            self.limiter.as_mut().map(|l| l as &mut dyn Effect),
+        ];
+        effects.into_iter().flatten()
     }
 }
+
+impl Effect for OutputEffects {
+    fn initialize(&mut self, sample_rate: usize, channels: usize) {
+        for effect in self.iter_mut() {
+            effect.initialize(sample_rate, channels);
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for effect in self.iter_mut() {
+            effect.process(samples);
+        }
+    }
+
+    fn reset(&mut self) {
+        for effect in self.iter_mut() {
+            effect.reset();
+        }
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) {
+        let mut tail = Vec::new();
+        for effect in self.iter_mut() {
+            effect.process(&mut tail);
+            effect.flush(&mut tail);
+        }
+        output.extend(tail);
+    }
+}
+
+// Dithers the conversion to the output when `T` or the device's format is a 16-bit or smaller
+// integer format
+pub(crate) fn create_dither<T: SizedSample>(
+    settings: Option<&DitherSettings>,
+    config: &SupportedStreamConfig,
+) -> Option<Dither> {
+    let bits = [T::FORMAT, config.sample_format()]
+        .into_iter()
+        .filter(|format| !format.is_float())
+        .map(|format| format.sample_size() as u32 * 8)
+        .min()
+        .filter(|bits| *bits <= 16)?;
+    let mut dither = Dither::new(settings?.clone(), bits);
+    dither.initialize(config.sample_rate().0 as usize, config.channels() as usize);
+    Some(dither)
+}
+
+#[cfg(test)]
+#[path = "./audio_manager_test.rs"]
+mod audio_manager_test;
@@ -0,0 +1,238 @@
+use std::f32::consts::PI;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use cpal::SampleFormat;
+
+use super::AudioManager;
+use crate::decoder::{
+    Decoder, DecoderResult, DecoderSettings, ReadSeekSource, ResamplerSettings, Source,
+};
+use crate::effects::{DitherSettings, LimiterSettings};
+use crate::output::{MockDevice, MockOutput};
+use crate::test_util::{mock_device, output_builder};
+
+fn source(path: &str) -> Box<dyn Source> {
+    Box::new(ReadSeekSource::from_path(Path::new(path)))
+}
+
+// A stereo 16-bit WAV file of a sine wave
+fn tone(sample_rate: u32, duration: Duration) -> Box<dyn Source> {
+    let frames = (duration.as_secs_f32() * sample_rate as f32) as u32;
+    let data_len = frames * 4;
+    let mut wav = Vec::new();
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, 2 channels
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 4).to_le_bytes());
+    wav.extend_from_slice(&4u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for frame in 0..frames {
+        let sample = 0.5 * (2.0 * PI * 440.0 * frame as f32 / sample_rate as f32).sin();
+        let sample = (sample * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&sample.to_le_bytes());
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    let len = wav.len() as u64;
+    Box::new(ReadSeekSource::new(
+        Cursor::new(wav),
+        Some(len),
+        Some("wav".to_owned()),
+    ))
+}
+
+// Reads from the output in the background like a device would until the flag is set
+fn play(device: MockDevice) -> (Arc<AtomicBool>, JoinHandle<Vec<f32>>) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = thread::spawn({
+        let stop = stop.clone();
+        move || {
+            let mut played = Vec::new();
+            while !stop.load(Ordering::SeqCst) {
+                played.extend(device.trigger_callback());
+                thread::sleep(Duration::from_millis(1));
+            }
+            played
+        }
+    });
+    (stop, handle)
+}
+
+#[test]
+fn fade_out_waits_for_output() {
+    let device = mock_device(SampleFormat::F32);
+    let mut manager =
+        AudioManager::<f32, _>::new(output_builder(device.clone()), ResamplerSettings::default())
+            .unwrap();
+    let mut decoder = manager
+        .init_decoder(source("examples/music.mp3"), DecoderSettings::default())
+        .unwrap();
+    manager.reset(&mut decoder).unwrap();
+
+    let (stop, player) = play(device);
+    manager.fade_out(&mut decoder).unwrap();
+    // Everything up to the end of the fade has been played
+    assert_eq!(0, manager.output.buffer_size());
+
+    stop.store(true, Ordering::SeqCst);
+    let played = player.join().unwrap();
+    assert!(played.iter().any(|s| *s != 0.0));
+}
+
+// Plays part of the file through a 16-bit device at a level below the smallest 16-bit step
+fn play_quietly(dither: Option<DitherSettings>) -> Vec<f32> {
+    let device = mock_device(SampleFormat::I16);
+    let mut manager =
+        AudioManager::<f32, _>::new(output_builder(device.clone()), ResamplerSettings::default())
+            .unwrap();
+    manager.set_dither(dither);
+    let mut decoder = Decoder::new(
+        source("examples/music.mp3"),
+        0.00001,
+        2,
+        DecoderSettings::default(),
+    )
+    .unwrap();
+    decoder.seek(Duration::from_secs(2)).unwrap();
+    manager.reset(&mut decoder).unwrap();
+    (0..8).flat_map(|_| device.trigger_callback()).collect()
+}
+
+#[test]
+fn dither_16_bit_device() {
+    let played = play_quietly(Some(DitherSettings::default()));
+    assert!(played.iter().any(|s| *s != 0.0));
+    // Only whole steps can be played, so nothing louder than the dither noise comes out
+    assert!(played.iter().all(|s| s.abs() <= 2.0 / 32768.0));
+
+    // Without dither everything is truncated to silence
+    let played = play_quietly(None);
+    assert!(played.iter().all(|s| *s == 0.0));
+}
+
+// Plays everything that's been written, leaving less than one callback's worth in the buffer
+fn play_buffered(
+    manager: &AudioManager<f32, MockOutput>,
+    device: &MockDevice,
+    played: &mut Vec<f32>,
+) {
+    while manager.output.buffer_size() >= 1024 {
+        played.extend(device.trigger_callback());
+    }
+}
+
+#[test]
+fn no_gap_between_sample_rates() {
+    let device = mock_device(SampleFormat::F32);
+    let mut manager =
+        AudioManager::<f32, _>::new(output_builder(device.clone()), ResamplerSettings::default())
+            .unwrap();
+    manager.set_limiter(Some(LimiterSettings::default()));
+    let mut played = Vec::new();
+
+    let mut first = manager
+        .init_decoder(
+            tone(44100, Duration::from_millis(300)),
+            DecoderSettings::default(),
+        )
+        .unwrap();
+    manager.reset(&mut first).unwrap();
+    assert_eq!(44100, manager.output_config.sample_rate().0);
+    loop {
+        play_buffered(&manager, &device, &mut played);
+        if manager.write(&mut first).unwrap() == DecoderResult::Finished {
+            break;
+        }
+    }
+
+    // The second file is resampled to the device's rate
+    let mut second = manager
+        .init_decoder(
+            tone(48000, Duration::from_millis(300)),
+            DecoderSettings::default(),
+        )
+        .unwrap();
+    manager.initialize(&mut second).unwrap();
+    for _ in 0..10 {
+        play_buffered(&manager, &device, &mut played);
+        manager.write(&mut second).unwrap();
+    }
+    play_buffered(&manager, &device, &mut played);
+
+    // Both files are a continuous tone, so any run of silence comes from the switch. The limiter's
+    // lookahead alone is 5ms, which is over 400 samples.
+    let start = played.iter().position(|s| *s != 0.0).unwrap();
+    let longest_silence = played[start..]
+        .split(|s| *s != 0.0)
+        .map(|run| run.len())
+        .max()
+        .unwrap();
+    assert!(longest_silence < 16);
+}
+
+#[test]
+fn select_track_with_a_different_format() {
+    let device = mock_device(SampleFormat::F32);
+    let mut manager =
+        AudioManager::<f32, _>::new(output_builder(device.clone()), ResamplerSettings::default())
+            .unwrap();
+    let mut played = Vec::new();
+
+    let mut decoder = manager
+        .init_decoder(
+            source("examples/multitrack.m4a"),
+            DecoderSettings::default(),
+        )
+        .unwrap();
+    let track_id = decoder.tracks()[1].id;
+    manager.reset(&mut decoder).unwrap();
+    assert_eq!(44100, manager.output_config.sample_rate().0);
+    for _ in 0..2 {
+        play_buffered(&manager, &device, &mut played);
+        manager.write(&mut decoder).unwrap();
+    }
+
+    // The second track is 48 kHz mono, which is resampled and upmixed to match the output
+    manager.select_track(&mut decoder, track_id).unwrap();
+    assert_eq!(track_id, decoder.track_id());
+    assert_eq!(48000, decoder.sample_rate());
+    assert_eq!(1, decoder.input_channels());
+    assert_eq!(2, decoder.output_channels());
+    assert_eq!(44100, manager.output_config.sample_rate().0);
+    loop {
+        play_buffered(&manager, &device, &mut played);
+        if manager.write(&mut decoder).unwrap() == DecoderResult::Finished {
+            break;
+        }
+    }
+    play_buffered(&manager, &device, &mut played);
+
+    // The first track's right channel is inverted, so the output goes from opposite channels to
+    // matching ones without stopping in between
+    let start = played.iter().position(|s| *s != 0.0).unwrap();
+    let end = played.iter().rposition(|s| *s != 0.0).unwrap();
+    let longest_silence = played[start..end]
+        .split(|s| *s != 0.0)
+        .map(|run| run.len())
+        .max()
+        .unwrap();
+    assert!(longest_silence < 16);
+    let frames: Vec<_> = played
+        .chunks_exact(2)
+        .filter(|frame| frame[0].abs() > 0.01)
+        .collect();
+    let (first, last) = (frames[0], frames[frames.len() - 1]);
+    assert!((first[0] + first[1]).abs() < 1e-4);
+    assert!((last[0] - last[1]).abs() < 1e-4);
+}
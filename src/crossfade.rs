@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_PI_2;
+use std::time::Duration;
+
+use dasp::sample::Sample as DaspSample;
+use symphonia::core::audio::conv::ConvertibleSample;
+use symphonia::core::audio::sample::Sample;
+use tracing::warn;
+
+use crate::decoder::{Decoder, DecoderError, DecoderResult, ResampledDecoder};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FadeCurve {
+    Linear,
+    /// Keeps the combined power constant, which avoids a dip in the middle of the crossfade for
+    /// uncorrelated material.
+    #[default]
+    EqualPower,
+    /// Eases in and out at the ends of the fade.
+    SCurve,
+}
+
+impl FadeCurve {
+    /// Gain for the incoming track at the given position of the fade, from 0 to 1.
+    pub fn fade_in(&self, position: f32) -> f32 {
+        let position = position.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => position,
+            Self::EqualPower => (position * FRAC_PI_2).sin(),
+            Self::SCurve => position * position * (3.0 - 2.0 * position),
+        }
+    }
+
+    /// Gain for the outgoing track at the given position of the fade, from 0 to 1.
+    pub fn fade_out(&self, position: f32) -> f32 {
+        self.fade_in(1.0 - position)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrossfadeSettings {
+    pub duration: Duration,
+    pub curve: FadeCurve,
+}
+
+impl Default for CrossfadeSettings {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(5),
+            curve: FadeCurve::default(),
+        }
+    }
+}
+
+// Holds the outgoing track while it's mixed with the incoming one
+pub(crate) struct Crossfade<T: Sample + DaspSample> {
+    decoder: Decoder<T>,
+    resampled: ResampledDecoder<T>,
+    pending: VecDeque<T>,
+    is_finished: bool,
+    curve: FadeCurve,
+    channels: usize,
+    position: usize,
+    frames: usize,
+}
+
+impl<T: Sample + DaspSample + ConvertibleSample + rubato::Sample> Crossfade<T> {
+    pub(crate) fn new(
+        decoder: Decoder<T>,
+        resampled: ResampledDecoder<T>,
+        settings: &CrossfadeSettings,
+        channels: usize,
+    ) -> Self {
+        let frames = settings.duration.as_secs_f64() * resampled.out_sample_rate() as f64;
+        Self {
+            decoder,
+            resampled,
+            pending: VecDeque::new(),
+            is_finished: false,
+            curve: settings.curve,
+            channels,
+            position: 0,
+            frames: frames.round() as usize,
+        }
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        self.position >= self.frames
+    }
+
+    pub(crate) fn remaining_frames(&self) -> usize {
+        self.frames - self.position
+    }
+
+    /// Fades in the incoming samples and mixes in the faded out samples from the outgoing track.
+    pub(crate) fn mix(&mut self, samples: &mut [T]) {
+        if let Err(e) = self.fill(samples.len()) {
+            warn!("Error decoding the outgoing track during the crossfade: {e:?}");
+            self.is_finished = true;
+        }
+
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let position = if self.frames == 0 {
+                1.0
+            } else {
+                self.position as f32 / self.frames as f32
+            };
+            let gain_in: T::Float = self.curve.fade_in(position).to_sample();
+            let gain_out: T::Float = self.curve.fade_out(position).to_sample();
+            for sample in frame {
+                let incoming = sample.to_float_sample() * gain_in;
+                *sample = match self.pending.pop_front() {
+                    Some(outgoing) => {
+                        (incoming + outgoing.to_float_sample() * gain_out).to_sample()
+                    }
+                    None => incoming.to_sample(),
+                };
+            }
+            self.position = (self.position + 1).min(self.frames);
+        }
+    }
+
+    // Decodes enough of the outgoing track to cover the requested number of samples
+    fn fill(&mut self, len: usize) -> Result<(), DecoderError> {
+        while self.pending.len() < len && !self.is_finished {
+            self.pending.extend(self.resampled.current(&self.decoder));
+            if self.resampled.decode_next_frame(&mut self.decoder)? == DecoderResult::Finished {
+                self.pending.extend(self.resampled.flush());
+                self.is_finished = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "./crossfade_test.rs"]
+mod crossfade_test;
@@ -0,0 +1,108 @@
+use std::path::Path;
+use std::time::Duration;
+
+use super::{Crossfade, CrossfadeSettings, FadeCurve};
+use crate::decoder::{
+    Decoder, DecoderSettings, ReadSeekSource, ResampledDecoder, ResamplerSettings,
+};
+
+// 100 ms at 44.1 kHz
+const FADE_FRAMES: usize = 4410;
+
+fn open_outgoing() -> Decoder<f32> {
+    let source = ReadSeekSource::from_path(Path::new("examples/music-1.mp3"));
+    Decoder::new(Box::new(source), 1.0, 2, DecoderSettings::default()).unwrap()
+}
+
+fn linear_crossfade() -> Crossfade<f32> {
+    let mut decoder = open_outgoing();
+    let mut resampled = ResampledDecoder::new(44100, 2, ResamplerSettings::default());
+    resampled.initialize(&mut decoder);
+    let settings = CrossfadeSettings {
+        duration: Duration::from_millis(100),
+        curve: FadeCurve::Linear,
+    };
+    Crossfade::new(decoder, resampled, &settings, 2)
+}
+
+// The start of the outgoing track without any fading
+fn outgoing_samples(len: usize) -> Vec<f32> {
+    let mut decoder = open_outgoing();
+    let mut samples = decoder.current().to_vec();
+    while samples.len() < len {
+        samples.extend_from_slice(decoder.next().unwrap().unwrap());
+    }
+    samples
+}
+
+#[test]
+fn fade_curve_endpoints() {
+    for curve in [FadeCurve::Linear, FadeCurve::EqualPower, FadeCurve::SCurve] {
+        assert_eq!(0.0, curve.fade_in(0.0));
+        assert_eq!(1.0, curve.fade_in(1.0));
+        assert_eq!(1.0, curve.fade_out(0.0));
+        assert_eq!(0.0, curve.fade_out(1.0));
+    }
+}
+
+#[test]
+fn equal_power_midpoint() {
+    let curve = FadeCurve::EqualPower;
+    let power = curve.fade_in(0.5).powi(2) + curve.fade_out(0.5).powi(2);
+
+    assert!((power - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn linear_midpoint() {
+    assert_eq!(0.5, FadeCurve::Linear.fade_in(0.5));
+    assert_eq!(0.5, FadeCurve::Linear.fade_out(0.5));
+    assert_eq!(0.5, FadeCurve::SCurve.fade_in(0.5));
+}
+
+#[test]
+fn overlap_length() {
+    let mut crossfade = linear_crossfade();
+    for _ in 0..4 {
+        crossfade.mix(&mut [0.0; 2000]);
+    }
+    assert!(!crossfade.is_complete());
+    assert_eq!(FADE_FRAMES - 4000, crossfade.remaining_frames());
+
+    crossfade.mix(&mut [0.0; 2000]);
+    assert!(crossfade.is_complete());
+}
+
+#[test]
+fn midpoint_mix() {
+    let mut crossfade = linear_crossfade();
+    let outgoing = outgoing_samples(FADE_FRAMES * 2);
+    let mut samples = vec![0.5; FADE_FRAMES * 2];
+    crossfade.mix(&mut samples);
+
+    // Both tracks are at half volume halfway through a linear fade
+    let midpoint = FADE_FRAMES / 2 * 2;
+    for i in midpoint..midpoint + 2 {
+        assert!((samples[i] - (0.25 + 0.5 * outgoing[i])).abs() < 1e-6);
+    }
+    assert!(crossfade.is_complete());
+}
+
+#[test]
+fn incoming_shorter_than_fade() {
+    let mut crossfade = linear_crossfade();
+    let outgoing = outgoing_samples(FADE_FRAMES * 2);
+    let mut samples = vec![0.0; 1000 * 2];
+    crossfade.mix(&mut samples);
+
+    // Once the incoming track runs out, the outgoing one keeps fading out over silence
+    let mut tail = vec![0.0; crossfade.remaining_frames() * 2];
+    crossfade.mix(&mut tail);
+    samples.extend(tail);
+    assert!(crossfade.is_complete());
+    assert_eq!(FADE_FRAMES * 2, samples.len());
+    for frame in [1000, 3000, FADE_FRAMES - 1] {
+        let gain = 1.0 - frame as f32 / FADE_FRAMES as f32;
+        assert!((samples[frame * 2] - outgoing[frame * 2] * gain).abs() < 1e-6);
+    }
+}
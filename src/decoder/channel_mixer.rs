@@ -0,0 +1,447 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use dasp::sample::Sample as DaspSample;
+use symphonia::core::audio::Channels;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    Lfe,
+    RearLeft,
+    RearRight,
+    FrontLeftCenter,
+    FrontRightCenter,
+    RearCenter,
+    SideLeft,
+    SideRight,
+    TopCenter,
+    TopFrontLeft,
+    TopFrontCenter,
+    TopFrontRight,
+    TopRearLeft,
+    TopRearCenter,
+    TopRearRight,
+    Other(usize),
+}
+
+// Symphonia guarantees that the first 18 channel positions match the bit order used by
+// WAVEFORMATEXTENSIBLE, which is also the order the channels are interleaved in
+const WAVE_ORDER: [Speaker; 18] = [
+    Speaker::FrontLeft,
+    Speaker::FrontRight,
+    Speaker::FrontCenter,
+    Speaker::Lfe,
+    Speaker::RearLeft,
+    Speaker::RearRight,
+    Speaker::FrontLeftCenter,
+    Speaker::FrontRightCenter,
+    Speaker::RearCenter,
+    Speaker::SideLeft,
+    Speaker::SideRight,
+    Speaker::TopCenter,
+    Speaker::TopFrontLeft,
+    Speaker::TopFrontCenter,
+    Speaker::TopFrontRight,
+    Speaker::TopRearLeft,
+    Speaker::TopRearCenter,
+    Speaker::TopRearRight,
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelLayout {
+    speakers: Vec<Speaker>,
+}
+
+impl ChannelLayout {
+    pub fn new(speakers: Vec<Speaker>) -> Self {
+        Self { speakers }
+    }
+
+    /// The conventional layout for a device or stream that only reports its channel count.
+    pub fn from_count(channels: usize) -> Self {
+        use Speaker::*;
+        let speakers = match channels {
+            1 => vec![FrontCenter],
+            2 => vec![FrontLeft, FrontRight],
+            3 => vec![FrontLeft, FrontRight, FrontCenter],
+            4 => vec![FrontLeft, FrontRight, RearLeft, RearRight],
+            5 => vec![FrontLeft, FrontRight, FrontCenter, RearLeft, RearRight],
+            6 => vec![FrontLeft, FrontRight, FrontCenter, Lfe, RearLeft, RearRight],
+            7 => vec![
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                Lfe,
+                RearCenter,
+                SideLeft,
+                SideRight,
+            ],
+            _ => WAVE_ORDER
+                .iter()
+                .copied()
+                .filter(|s| !matches!(s, FrontLeftCenter | FrontRightCenter | RearCenter))
+                .chain([FrontLeftCenter, FrontRightCenter, RearCenter])
+                .chain((WAVE_ORDER.len()..).map(Other))
+                .take(channels)
+                .collect(),
+        };
+        Self { speakers }
+    }
+
+    pub(crate) fn from_channels(channels: &Channels) -> Self {
+        let count = channels.count();
+        // Mono sources are reported with inconsistent positions depending on the format, so
+        // they're always treated as centered
+        if let (Channels::Positioned(positions), 2..) = (channels, count) {
+            let speakers: Vec<_> = WAVE_ORDER
+                .iter()
+                .enumerate()
+                .filter(|(bit, _)| positions.bits() & (1 << bit) != 0)
+                .map(|(_, speaker)| *speaker)
+                .collect();
+            if speakers.len() == count {
+                return Self { speakers };
+            }
+        }
+        Self::from_count(count)
+    }
+
+    pub fn speakers(&self) -> &[Speaker] {
+        &self.speakers
+    }
+
+    pub fn len(&self) -> usize {
+        self.speakers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.speakers.is_empty()
+    }
+
+    pub fn index_of(&self, speaker: Speaker) -> Option<usize> {
+        self.speakers.iter().position(|s| *s == speaker)
+    }
+
+    fn is_mono(&self) -> bool {
+        self.speakers == [Speaker::FrontCenter]
+    }
+}
+
+/// Gains used to mix each input channel into each output channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelMatrix {
+    input_channels: usize,
+    output_channels: usize,
+    // Row-major, one row per output channel
+    coefficients: Vec<f32>,
+}
+
+impl ChannelMatrix {
+    pub fn new(input_channels: usize, output_channels: usize) -> Self {
+        Self {
+            input_channels,
+            output_channels,
+            coefficients: vec![0.0; input_channels * output_channels],
+        }
+    }
+
+    /// Creates a matrix from one row of input gains per output channel.
+    pub fn from_rows(rows: Vec<Vec<f32>>) -> Option<Self> {
+        let input_channels = rows.first()?.len();
+        if rows.iter().any(|row| row.len() != input_channels) {
+            return None;
+        }
+        Some(Self {
+            input_channels,
+            output_channels: rows.len(),
+            coefficients: rows.concat(),
+        })
+    }
+
+    pub fn identity(channels: usize) -> Self {
+        let mut matrix = Self::new(channels, channels);
+        for i in 0..channels {
+            matrix.set(i, i, 1.0);
+        }
+        matrix
+    }
+
+    /// Standard downmix coefficients based on ITU-R BS.775. Channels that exist in both
+    /// layouts are passed through unchanged.
+    pub fn standard(input: &ChannelLayout, output: &ChannelLayout) -> Self {
+        let stereo = ChannelLayout::from_count(2);
+        if output.is_mono() && !input.is_mono() {
+            // Fold down to stereo first so every input channel contributes to the mono output
+            let folded = Self::standard(input, &stereo);
+            let mut matrix = Self::new(input.len(), 1);
+            for i in 0..input.len() {
+                matrix.set(0, i, 0.5 * (folded.get(0, i) + folded.get(1, i)));
+            }
+            return matrix;
+        }
+
+        let mut matrix = Self::new(input.len(), output.len());
+        for (i, speaker) in input.speakers().iter().enumerate() {
+            if input.is_mono() && !output.is_mono() {
+                for front in [Speaker::FrontLeft, Speaker::FrontRight] {
+                    if let Some(o) = output.index_of(front) {
+                        matrix.set(o, i, 1.0);
+                    }
+                }
+                continue;
+            }
+            if let Some(o) = output.index_of(*speaker) {
+                matrix.set(o, i, 1.0);
+                continue;
+            }
+            if let Some(targets) = fallbacks(*speaker)
+                .iter()
+                .find(|targets| targets.iter().all(|(s, _)| output.index_of(*s).is_some()))
+            {
+                for (target, gain) in targets.iter() {
+                    if let Some(o) = output.index_of(*target) {
+                        matrix.set(o, i, *gain);
+                    }
+                }
+            }
+        }
+        matrix
+    }
+
+    /// Like [`ChannelMatrix::standard`], but surround channels that would otherwise be silent
+    /// receive a copy of the matching front channel.
+    pub fn duplicated(input: &ChannelLayout, output: &ChannelLayout) -> Self {
+        use Speaker::*;
+        let mut matrix = Self::standard(input, output);
+        let front_row = |matrix: &Self, speaker| {
+            output
+                .index_of(speaker)
+                .map(|o| matrix.row(o).to_vec())
+                .unwrap_or_else(|| vec![0.0; input.len()])
+        };
+        let left = front_row(&matrix, FrontLeft);
+        let right = front_row(&matrix, FrontRight);
+
+        for (o, speaker) in output.speakers().iter().enumerate() {
+            if matrix.row(o).iter().any(|c| *c != 0.0) {
+                continue;
+            }
+            for i in 0..input.len() {
+                let gain = match speaker {
+                    RearLeft | SideLeft => left[i],
+                    RearRight | SideRight => right[i],
+                    RearCenter => 0.5 * (left[i] + right[i]),
+                    _ => continue,
+                };
+                matrix.set(o, i, gain);
+            }
+        }
+        matrix
+    }
+
+    /// Scales the matrix so that no output channel can exceed full scale.
+    pub fn normalized(mut self) -> Self {
+        let max_gain = (0..self.output_channels)
+            .map(|o| self.row(o).iter().map(|c| c.abs()).sum::<f32>())
+            .fold(0.0, f32::max);
+        if max_gain > 1.0 {
+            self.coefficients.iter_mut().for_each(|c| *c /= max_gain);
+        }
+        self
+    }
+
+    pub fn input_channels(&self) -> usize {
+        self.input_channels
+    }
+
+    pub fn output_channels(&self) -> usize {
+        self.output_channels
+    }
+
+    pub fn get(&self, output: usize, input: usize) -> f32 {
+        self.coefficients[output * self.input_channels + input]
+    }
+
+    pub fn set(&mut self, output: usize, input: usize, gain: f32) {
+        self.coefficients[output * self.input_channels + input] = gain;
+    }
+
+    pub fn row(&self, output: usize) -> &[f32] {
+        &self.coefficients[output * self.input_channels..(output + 1) * self.input_channels]
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity(self.input_channels)
+    }
+}
+
+fn fallbacks(speaker: Speaker) -> &'static [&'static [(Speaker, f32)]] {
+    use Speaker::*;
+    const M3DB: f32 = FRAC_1_SQRT_2;
+    match speaker {
+        FrontCenter => &[&[(FrontLeft, M3DB), (FrontRight, M3DB)]],
+        FrontLeftCenter => &[
+            &[(FrontLeft, M3DB), (FrontCenter, M3DB)],
+            &[(FrontLeft, 1.0)],
+        ],
+        FrontRightCenter => &[
+            &[(FrontRight, M3DB), (FrontCenter, M3DB)],
+            &[(FrontRight, 1.0)],
+        ],
+        RearLeft => &[&[(SideLeft, 1.0)], &[(FrontLeft, M3DB)]],
+        RearRight => &[&[(SideRight, 1.0)], &[(FrontRight, M3DB)]],
+        SideLeft => &[&[(RearLeft, 1.0)], &[(FrontLeft, M3DB)]],
+        SideRight => &[&[(RearRight, 1.0)], &[(FrontRight, M3DB)]],
+        RearCenter => &[
+            &[(RearLeft, M3DB), (RearRight, M3DB)],
+            &[(SideLeft, M3DB), (SideRight, M3DB)],
+            &[(FrontLeft, 0.5), (FrontRight, 0.5)],
+        ],
+        TopFrontLeft => &[&[(FrontLeft, M3DB)]],
+        TopFrontRight => &[&[(FrontRight, M3DB)]],
+        TopFrontCenter => &[
+            &[(FrontCenter, M3DB)],
+            &[(FrontLeft, 0.5), (FrontRight, 0.5)],
+        ],
+        TopRearLeft => &[
+            &[(RearLeft, M3DB)],
+            &[(SideLeft, M3DB)],
+            &[(FrontLeft, 0.5)],
+        ],
+        TopRearRight => &[
+            &[(RearRight, M3DB)],
+            &[(SideRight, M3DB)],
+            &[(FrontRight, 0.5)],
+        ],
+        TopRearCenter | TopCenter => &[&[(FrontLeft, 0.5), (FrontRight, 0.5)]],
+        // The LFE channel is discarded when the output has no dedicated channel for it
+        FrontLeft | FrontRight | Lfe | Other(_) => &[],
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum DownmixMode {
+    /// ITU-R BS.775 coefficients. Loud surround content can exceed full scale.
+    #[default]
+    Itu,
+    /// ITU-R BS.775 coefficients scaled down so the output can't exceed full scale.
+    ItuNormalized,
+    Custom(ChannelMatrix),
+}
+
+impl DownmixMode {
+    pub(crate) fn matrix(&self, input: &ChannelLayout, output: &ChannelLayout) -> ChannelMatrix {
+        match self {
+            Self::Itu => ChannelMatrix::standard(input, output),
+            Self::ItuNormalized => ChannelMatrix::standard(input, output).normalized(),
+            Self::Custom(matrix)
+                if matrix.input_channels() == input.len()
+                    && matrix.output_channels() == output.len() =>
+            {
+                matrix.clone()
+            }
+            Self::Custom(matrix) => {
+                tracing::warn!(
+                    "Custom downmix matrix is {}x{} but the source requires {}x{}. Using the \
+                     standard matrix instead",
+                    matrix.input_channels(),
+                    matrix.output_channels(),
+                    input.len(),
+                    output.len()
+                );
+                ChannelMatrix::standard(input, output)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum UpmixMode {
+    /// Only the front channels of the output receive audio.
+    #[default]
+    FrontOnly,
+    /// The front channels are copied to the surround channels.
+    Duplicate,
+    Custom(ChannelMatrix),
+}
+
+impl UpmixMode {
+    pub(crate) fn matrix(&self, input: &ChannelLayout, output: &ChannelLayout) -> ChannelMatrix {
+        match self {
+            Self::FrontOnly => ChannelMatrix::standard(input, output),
+            Self::Duplicate => ChannelMatrix::duplicated(input, output),
+            Self::Custom(matrix)
+                if matrix.input_channels() == input.len()
+                    && matrix.output_channels() == output.len() =>
+            {
+                matrix.clone()
+            }
+            Self::Custom(matrix) => {
+                tracing::warn!(
+                    "Custom upmix matrix is {}x{} but the source requires {}x{}. Using the \
+                     standard matrix instead",
+                    matrix.input_channels(),
+                    matrix.output_channels(),
+                    input.len(),
+                    output.len()
+                );
+                ChannelMatrix::standard(input, output)
+            }
+        }
+    }
+}
+
+pub(crate) struct ChannelMixer<T: DaspSample> {
+    input_channels: usize,
+    output_channels: usize,
+    coefficients: Vec<T::Float>,
+    is_identity: bool,
+}
+
+impl<T: DaspSample> ChannelMixer<T> {
+    pub(crate) fn new(matrix: &ChannelMatrix) -> Self {
+        Self {
+            input_channels: matrix.input_channels(),
+            output_channels: matrix.output_channels(),
+            coefficients: matrix.coefficients.iter().map(|c| c.to_sample()).collect(),
+            is_identity: matrix.is_identity(),
+        }
+    }
+
+    pub(crate) fn output_len(&self, input_len: usize) -> usize {
+        input_len / self.input_channels * self.output_channels
+    }
+
+    pub(crate) fn mix(&self, input: &[T], output: &mut [T], volume: T::Float) {
+        if self.is_identity {
+            for (out, sample) in output.iter_mut().zip(input) {
+                *out = sample.mul_amp(volume);
+            }
+            return;
+        }
+
+        for (in_frame, out_frame) in input
+            .chunks_exact(self.input_channels)
+            .zip(output.chunks_exact_mut(self.output_channels))
+        {
+            for (out, row) in out_frame
+                .iter_mut()
+                .zip(self.coefficients.chunks_exact(self.input_channels))
+            {
+                let mixed = in_frame
+                    .iter()
+                    .zip(row)
+                    .fold(T::Float::EQUILIBRIUM, |acc, (sample, gain)| {
+                        acc + sample.to_float_sample() * *gain
+                    });
+                *out = (mixed * volume).to_sample();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./channel_mixer_test.rs"]
+mod channel_mixer_test;
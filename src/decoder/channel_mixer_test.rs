@@ -0,0 +1,118 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use super::{ChannelLayout, ChannelMatrix, ChannelMixer, DownmixMode, Speaker, UpmixMode};
+
+#[test]
+fn downmix_5_1_to_stereo() {
+    let matrix =
+        ChannelMatrix::standard(&ChannelLayout::from_count(6), &ChannelLayout::from_count(2));
+
+    // FL, FR, FC, LFE, RL, RR
+    assert_eq!(
+        &[1.0, 0.0, FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2, 0.0],
+        matrix.row(0)
+    );
+    assert_eq!(
+        &[0.0, 1.0, FRAC_1_SQRT_2, 0.0, 0.0, FRAC_1_SQRT_2],
+        matrix.row(1)
+    );
+}
+
+#[test]
+fn downmix_7_1_to_5_1() {
+    let matrix =
+        ChannelMatrix::standard(&ChannelLayout::from_count(8), &ChannelLayout::from_count(6));
+
+    // Side channels are merged into the rear channels
+    assert_eq!(1.0, matrix.get(4, 6));
+    assert_eq!(1.0, matrix.get(5, 7));
+    assert_eq!(1.0, matrix.get(3, 3));
+}
+
+#[test]
+fn downmix_stereo_to_mono() {
+    let matrix =
+        ChannelMatrix::standard(&ChannelLayout::from_count(2), &ChannelLayout::from_count(1));
+
+    assert_eq!(&[0.5, 0.5], matrix.row(0));
+}
+
+#[test]
+fn downmix_normalized() {
+    let matrix = DownmixMode::ItuNormalized
+        .matrix(&ChannelLayout::from_count(6), &ChannelLayout::from_count(2));
+    let gain: f32 = matrix.row(0).iter().sum();
+
+    assert!((gain - 1.0).abs() < 0.0001);
+}
+
+#[test]
+fn downmix_custom_mismatch() {
+    let custom = ChannelMatrix::from_rows(vec![vec![1.0, 0.0]]).unwrap();
+    let matrix = DownmixMode::Custom(custom)
+        .matrix(&ChannelLayout::from_count(6), &ChannelLayout::from_count(2));
+
+    assert_eq!(
+        ChannelMatrix::standard(&ChannelLayout::from_count(6), &ChannelLayout::from_count(2)),
+        matrix
+    );
+}
+
+#[test]
+fn layout_from_count() {
+    let layout = ChannelLayout::from_count(8);
+
+    assert_eq!(Some(6), layout.index_of(Speaker::SideLeft));
+    assert_eq!(Some(7), layout.index_of(Speaker::SideRight));
+    assert_eq!(20, ChannelLayout::from_count(20).len());
+}
+
+#[test]
+fn mix_samples() {
+    let matrix =
+        ChannelMatrix::standard(&ChannelLayout::from_count(6), &ChannelLayout::from_count(2));
+    let mixer = ChannelMixer::<f32>::new(&matrix);
+    let input = [0.5, 0.25, 0.0, 1.0, 0.0, 0.0];
+    let mut output = [0.0; 2];
+    mixer.mix(&input, &mut output, 0.5);
+
+    assert_eq!([0.25, 0.125], output);
+}
+
+#[test]
+fn upmix_stereo_front_only() {
+    let matrix =
+        UpmixMode::FrontOnly.matrix(&ChannelLayout::from_count(2), &ChannelLayout::from_count(6));
+
+    assert_eq!(&[1.0, 0.0], matrix.row(0));
+    assert_eq!(&[0.0, 1.0], matrix.row(1));
+    for o in 2..6 {
+        assert_eq!(&[0.0, 0.0], matrix.row(o));
+    }
+}
+
+#[test]
+fn upmix_stereo_duplicate() {
+    let matrix =
+        UpmixMode::Duplicate.matrix(&ChannelLayout::from_count(2), &ChannelLayout::from_count(8));
+
+    // FL, FR, FC, LFE, RL, RR, SL, SR
+    assert_eq!(&[0.0, 0.0], matrix.row(2));
+    assert_eq!(&[0.0, 0.0], matrix.row(3));
+    for o in [4, 6] {
+        assert_eq!(&[1.0, 0.0], matrix.row(o));
+    }
+    for o in [5, 7] {
+        assert_eq!(&[0.0, 1.0], matrix.row(o));
+    }
+}
+
+#[test]
+fn upmix_mono_duplicate() {
+    let matrix =
+        UpmixMode::Duplicate.matrix(&ChannelLayout::from_count(1), &ChannelLayout::from_count(4));
+
+    for o in 0..4 {
+        assert_eq!(&[1.0], matrix.row(o));
+    }
+}
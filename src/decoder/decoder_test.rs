@@ -0,0 +1,380 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use symphonia::core::io::MediaSource;
+
+use super::{
+    Decoder, DecoderError, DecoderSettings, FileExt, ReadSeekSource, SeekAccuracy, SeekDirection,
+    Source, UpmixMode,
+};
+
+fn open(path: &str, settings: DecoderSettings) -> Decoder<f32> {
+    let source = ReadSeekSource::from_path(Path::new(path));
+    Decoder::new(Box::new(source), 1.0, 2, settings).unwrap()
+}
+
+// Decodes until the pause fade has finished and only silence is left
+fn finish_pause_fade(decoder: &mut Decoder<f32>) {
+    while decoder.next().unwrap().unwrap().iter().any(|s| *s != 0.0) {}
+}
+
+// The id of the 48 kHz mono track in the multi-track example. The first track is 44.1 kHz stereo.
+fn second_track(decoder: &Decoder<f32>) -> u32 {
+    let tracks = decoder.tracks();
+    assert_eq!(2, tracks.len());
+    assert_eq!(
+        (Some(44100), Some(2)),
+        (tracks[0].sample_rate, tracks[0].channels)
+    );
+    assert_eq!(
+        (Some(48000), Some(1)),
+        (tracks[1].sample_rate, tracks[1].channels)
+    );
+    tracks[1].id
+}
+
+#[test]
+fn selecting_missing_track_fails() {
+    let mut decoder = open("examples/music.mp3", DecoderSettings::default());
+    assert!(matches!(
+        decoder.select_track(1000),
+        Err(DecoderError::TrackNotFound(1000))
+    ));
+}
+
+#[test]
+fn switching_track_changes_format() {
+    let mut decoder = open("examples/multitrack.m4a", DecoderSettings::default());
+    let track_id = second_track(&decoder);
+    assert_ne!(track_id, decoder.track_id());
+    assert_eq!(44100, decoder.sample_rate());
+    decoder.next().unwrap().unwrap();
+    let position = decoder.current_position().position;
+
+    decoder.select_track(track_id).unwrap();
+    assert_eq!(track_id, decoder.track_id());
+    assert_eq!(48000, decoder.sample_rate());
+    assert_eq!(1, decoder.input_channels());
+    assert_eq!(2, decoder.output_channels());
+    let new_position = decoder.current_position().position;
+    assert!(new_position.abs_diff(position) < Duration::from_millis(100));
+
+    // The mono track is played in both channels
+    let samples = decoder.next().unwrap().unwrap();
+    assert!(samples.iter().any(|s| *s != 0.0));
+    assert!(samples.chunks_exact(2).all(|frame| frame[0] == frame[1]));
+}
+
+#[test]
+fn switching_track_while_paused() {
+    let mut decoder = open("examples/multitrack.m4a", DecoderSettings::default());
+    let track_id = second_track(&decoder);
+    decoder.pause();
+    finish_pause_fade(&mut decoder);
+    let position = decoder.current_position().position;
+
+    // Nothing is decoded while paused, so the format has to come from the track
+    decoder.select_track(track_id).unwrap();
+    assert_eq!(48000, decoder.sample_rate());
+    assert_eq!(1, decoder.input_channels());
+    let new_position = decoder.current_position().position;
+    assert!(new_position.abs_diff(position) < Duration::from_millis(100));
+    assert!(decoder.next().unwrap().unwrap().iter().all(|s| *s == 0.0));
+
+    decoder.resume();
+    let samples = decoder.next().unwrap().unwrap();
+    assert!(samples.iter().any(|s| *s != 0.0));
+    assert!(samples.chunks_exact(2).all(|frame| frame[0] == frame[1]));
+}
+
+#[test]
+fn main_output_channels() {
+    let mut decoder = open("examples/music.mp3", DecoderSettings::default());
+    decoder.set_output_channels(6);
+    assert_eq!(
+        vec![true, true, false, false, false, false],
+        decoder.main_output_channels()
+    );
+
+    let mut decoder = open("examples/music.mp3", DecoderSettings {
+        upmix: UpmixMode::Duplicate,
+        ..Default::default()
+    });
+    decoder.set_output_channels(6);
+    assert_eq!(
+        vec![true, true, false, false, true, true],
+        decoder.main_output_channels()
+    );
+}
+
+#[test]
+fn seek_by_stops_at_start() {
+    let mut decoder = open("examples/music.mp3", DecoderSettings::default());
+    decoder.seek(Duration::from_secs(2)).unwrap();
+    let position = decoder
+        .seek_by(Duration::from_secs(60), SeekDirection::Backward)
+        .unwrap();
+    assert!(position < Duration::from_millis(50));
+}
+
+#[test]
+fn seek_by_past_end_finishes() {
+    let mut decoder = open("examples/music.mp3", DecoderSettings::default());
+    let duration = decoder.duration().unwrap().duration;
+    let position = decoder
+        .seek_by(Duration::from_secs(60), SeekDirection::Forward)
+        .unwrap();
+    assert!(duration.abs_diff(position) < Duration::from_millis(50));
+
+    // At most the last packet is left to play
+    let mut packets = 0;
+    while decoder.next().unwrap().is_some() {
+        packets += 1;
+        assert!(packets <= 2);
+    }
+}
+
+#[test]
+fn seek_to_frame() {
+    let mut decoder = open("examples/music.mp3", DecoderSettings::default());
+    let position = decoder.seek_to_frame(44100 * 3).unwrap();
+    assert!(position.abs_diff(Duration::from_secs(3)) < Duration::from_millis(30));
+}
+
+#[test]
+fn coarse_seek_to_frame_reports_packet_start() {
+    let mut decoder = open("examples/music.mp3", DecoderSettings {
+        seek_accuracy: SeekAccuracy::Coarse,
+        ..Default::default()
+    });
+    // Halfway through a packet
+    let position = decoder.seek_to_frame(1152 * 100 + 576).unwrap();
+    decoder.next().unwrap().unwrap();
+    assert_eq!(position, decoder.current_position().position);
+}
+
+#[test]
+fn coarse_seek_by_reports_packet_start() {
+    let mut decoder = open("examples/music.mp3", DecoderSettings {
+        seek_accuracy: SeekAccuracy::Coarse,
+        ..Default::default()
+    });
+    decoder.seek(Duration::from_secs(5)).unwrap();
+    let position = decoder
+        .seek_by(Duration::from_millis(1234), SeekDirection::Backward)
+        .unwrap();
+    decoder.next().unwrap().unwrap();
+    assert_eq!(position, decoder.current_position().position);
+}
+
+#[test]
+fn duration_from_header() {
+    let decoder = open("examples/music.mp3", DecoderSettings::default());
+    let duration = decoder.duration().unwrap();
+    assert!(!duration.is_estimated);
+    assert!(duration.duration.abs_diff(Duration::from_millis(10214)) < Duration::from_millis(100));
+}
+
+// Knows its length but can't seek, like a download with a known content length. Formats can't
+// scan the file for its length in that case.
+#[derive(Debug)]
+struct UnseekableSource(ReadSeekSource<BufReader<File>>);
+
+impl MediaSource for UnseekableSource {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.0.byte_len()
+    }
+}
+
+impl Read for UnseekableSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Seek for UnseekableSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+impl FileExt for UnseekableSource {
+    fn get_file_ext(&self) -> Option<String> {
+        self.0.get_file_ext()
+    }
+}
+
+impl Source for UnseekableSource {
+    fn as_media_source(self: Box<Self>) -> Box<dyn MediaSource> {
+        self
+    }
+}
+
+#[test]
+fn duration_estimated_from_bitrate() {
+    // A VBR file without a Xing header, so it doesn't declare its length
+    let source = ReadSeekSource::from_path(Path::new("examples/music-no-header.mp3"));
+    let mut decoder = Decoder::<f32>::new(
+        Box::new(UnseekableSource(source)),
+        1.0,
+        2,
+        DecoderSettings::default(),
+    )
+    .unwrap();
+    for _ in 0..20 {
+        decoder.next().unwrap();
+    }
+    let duration = decoder.duration().unwrap();
+    assert!(duration.is_estimated);
+    // 120 packets of 1152 frames
+    assert!(duration.duration.abs_diff(Duration::from_millis(3135)) < Duration::from_millis(300));
+}
+
+#[test]
+fn progress() {
+    let mut decoder = open("examples/music.mp3", DecoderSettings::default());
+    assert!(decoder.progress().unwrap() < 0.01);
+
+    let duration = decoder.duration().unwrap().duration;
+    decoder.seek(duration / 2).unwrap();
+    assert!((decoder.progress().unwrap() - 0.5).abs() < 0.01);
+}
+
+#[test]
+fn accurate_seek() {
+    let mut decoder = open("examples/music.mp3", DecoderSettings::default());
+    let target = Duration::from_millis(1500);
+    let position = decoder
+        .seek_with_accuracy(target, SeekAccuracy::Accurate)
+        .unwrap();
+    assert_eq!(target, position);
+
+    // The frames before the target are trimmed from the packet containing it
+    let frames = decoder.next().unwrap().unwrap().len() / 2;
+    assert!(frames < 1152);
+    assert_eq!(target, decoder.current_position().position);
+}
+
+// Decodes the rest of the file and returns the number of frames
+fn decode_to_end(decoder: &mut Decoder<f32>) -> usize {
+    let mut frames = decoder.current().len() / 2;
+    while let Some(samples) = decoder.next().unwrap() {
+        frames += samples.len() / 2;
+    }
+    frames
+}
+
+#[test]
+fn gapless_removes_delay_and_padding() {
+    let mut decoder = open("examples/music-1.mp3", DecoderSettings {
+        enable_gapless: true,
+        ..Default::default()
+    });
+    let num_frames = decoder.num_frames.unwrap() as usize;
+    let gapless_frames = decode_to_end(&mut decoder);
+    assert_eq!(num_frames, gapless_frames);
+
+    let mut decoder = open("examples/music-1.mp3", DecoderSettings::default());
+    let frames = decode_to_end(&mut decoder);
+    // This file declares 576 frames of delay and 1260 frames of padding
+    assert!(frames >= gapless_frames + 1836);
+}
+
+fn trimming_decoder() -> Decoder<f32> {
+    let mut decoder = open("examples/music.mp3", DecoderSettings {
+        trim_trailing_silence: true,
+        ..Default::default()
+    });
+    // Skip the silence at the start of the file
+    decoder.seek(Duration::from_secs(2)).unwrap();
+    decoder
+}
+
+// Replaces the decoded buffer and runs it through the silence trimming
+fn defer_silence(decoder: &mut Decoder<f32>, samples: &[f32]) -> Option<Vec<f32>> {
+    decoder.buf.clear();
+    decoder.buf.extend_from_slice(samples);
+    decoder.buf_len = samples.len();
+    decoder
+        .defer_trailing_silence()
+        .then(|| decoder.current().to_vec())
+}
+
+#[test]
+fn trailing_silence_is_held_back() {
+    let mut decoder = trimming_decoder();
+    let packet = [[0.5; 200].as_slice(), &[0.0; 100]].concat();
+    assert_eq!(Some(vec![0.5; 200]), defer_silence(&mut decoder, &packet));
+    assert_eq!(None, defer_silence(&mut decoder, &[0.0; 300]));
+    assert_eq!(200, decoder.pending_silence_frames);
+}
+
+#[test]
+fn silence_before_audio_is_put_back() {
+    let mut decoder = trimming_decoder();
+    let packet = [[0.5; 200].as_slice(), &[0.0; 100]].concat();
+    defer_silence(&mut decoder, &packet);
+    defer_silence(&mut decoder, &[0.0; 300]);
+
+    let samples = defer_silence(&mut decoder, &[0.25; 100]).unwrap();
+    assert_eq!([[0.0; 400].as_slice(), &[0.25; 100]].concat(), samples);
+    assert_eq!(0, decoder.pending_silence_frames);
+}
+
+#[test]
+fn long_silence_is_released() {
+    let mut decoder = trimming_decoder();
+    // Two seconds at 44.1 kHz
+    let max_frames = 88200;
+    let packet = vec![0.0; 2304];
+    let mut released = 0;
+    for _ in 0..100 {
+        if let Some(samples) = defer_silence(&mut decoder, &packet) {
+            // Only the silence past the limit comes out, one packet at a time
+            assert!(samples.len() <= packet.len());
+            released += samples.len() / 2;
+        }
+    }
+    assert_eq!(max_frames, decoder.pending_silence_frames);
+    assert_eq!(100 * 1152 - max_frames, released);
+}
+
+#[test]
+fn pause_and_resume_keep_the_rest_of_the_packet() {
+    let mut reference = open("examples/music.mp3", DecoderSettings::default());
+    let mut decoder = open("examples/music.mp3", DecoderSettings::default());
+    for _ in 0..10 {
+        reference.next().unwrap();
+        decoder.next().unwrap();
+    }
+    let faded_packet = reference.next().unwrap().unwrap().to_vec();
+    let next_packet = reference.next().unwrap().unwrap().to_vec();
+
+    // The 20 ms fade takes 882 of the packet's 1152 frames
+    decoder.pause();
+    let fade_len = 882 * 2;
+    let samples = decoder.next().unwrap().unwrap().to_vec();
+    assert_eq!(faded_packet.len(), samples.len());
+    assert!(samples[fade_len..].iter().all(|s| *s == 0.0));
+    assert!(decoder.next().unwrap().unwrap().iter().all(|s| *s == 0.0));
+
+    // Resuming fades back in from where the fade out ended
+    decoder.resume();
+    let remainder = decoder.next().unwrap().unwrap().to_vec();
+    assert_eq!(faded_packet.len() - fade_len, remainder.len());
+    for (i, frame) in remainder.chunks_exact(2).enumerate() {
+        let gain = (i + 1) as f32 / 882.0;
+        let expected = faded_packet[fade_len + i * 2] * gain;
+        assert!((frame[0] - expected).abs() < 1e-3);
+    }
+    // The rest of the fade in is finished in the next packet
+    let samples = decoder.next().unwrap().unwrap();
+    let fade_in_len = fade_len - remainder.len();
+    assert_eq!(next_packet[fade_in_len..], samples[fade_in_len..]);
+}
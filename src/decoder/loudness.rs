@@ -0,0 +1,249 @@
+use std::f64::consts::PI;
+
+use super::{ChannelLayout, Decoder, DecoderError, DecoderSettings, ReplayGain, Source, Speaker};
+use crate::effects::{BiquadCoefficients, BiquadState, TruePeakDetector};
+
+// Loudness is measured in 100ms segments. Gating blocks are made from several consecutive
+// segments, which gives the 75% overlap required for momentary loudness.
+const SEGMENTS_PER_SECOND: usize = 10;
+const MOMENTARY_SEGMENTS: usize = 4;
+const SHORT_TERM_SEGMENTS: usize = 30;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+const RANGE_LOW_PERCENTILE: f64 = 0.10;
+const RANGE_HIGH_PERCENTILE: f64 = 0.95;
+// ReplayGain 2.0 reference level
+const REPLAY_GAIN_REFERENCE_LUFS: f64 = -18.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS. This is negative infinity if the source is silent.
+    pub integrated_lufs: f64,
+    /// Loudness range in LU.
+    pub range_lu: f64,
+    /// Linear sample peak measured with 4x oversampling.
+    pub true_peak: f64,
+}
+
+impl Loudness {
+    pub fn true_peak_dbtp(&self) -> f64 {
+        20.0 * self.true_peak.log10()
+    }
+
+    /// Converts the measurement into track gain values that can be passed to
+    /// [`Decoder::set_replay_gain`].
+    pub fn replay_gain(&self) -> ReplayGain {
+        let track_gain_db = if self.integrated_lufs.is_finite() {
+            Some((REPLAY_GAIN_REFERENCE_LUFS - self.integrated_lufs) as f32)
+        } else {
+            None
+        };
+        ReplayGain {
+            track_gain_db,
+            track_peak: Some(self.true_peak as f32),
+            ..Default::default()
+        }
+    }
+}
+
+/// Decodes the entire source and measures its loudness according to EBU R128.
+pub fn analyze_loudness(source: Box<dyn Source>) -> Result<Loudness, DecoderError> {
+    let settings = DecoderSettings {
+        enable_gapless: true,
+        ..Default::default()
+    };
+    let mut decoder = Decoder::<f32>::new(source, 1.0, 2, settings)?;
+    // Measure the original channels rather than a downmix
+    decoder.set_output_channels(decoder.input_channels());
+
+    let mut analyzer = LoudnessAnalyzer::new(decoder.sample_rate(), decoder.input_layout());
+    analyzer.process(decoder.current());
+    while let Some(samples) = decoder.next()? {
+        analyzer.process(samples);
+    }
+    Ok(analyzer.loudness())
+}
+
+/// Measures loudness incrementally from interleaved samples.
+pub struct LoudnessAnalyzer {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<KWeightingFilter>,
+    peak_detectors: Vec<TruePeakDetector>,
+    true_peak: f32,
+    segment_len: usize,
+    segment_pos: usize,
+    segment_power: Vec<f64>,
+    segments: Vec<f64>,
+}
+
+impl LoudnessAnalyzer {
+    pub fn new(sample_rate: usize, layout: &ChannelLayout) -> Self {
+        Self {
+            channels: layout.len(),
+            weights: layout
+                .speakers()
+                .iter()
+                .map(|s| channel_weight(*s))
+                .collect(),
+            filters: vec![KWeightingFilter::new(sample_rate as f64); layout.len()],
+            peak_detectors: vec![TruePeakDetector::new(); layout.len()],
+            true_peak: 0.0,
+            segment_len: (sample_rate / SEGMENTS_PER_SECOND).max(1),
+            segment_pos: 0,
+            segment_power: vec![0.0; layout.len()],
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn process(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, sample) in frame.iter().enumerate() {
+                let peak = self.peak_detectors[channel].process(*sample);
+                self.true_peak = self.true_peak.max(peak);
+                let filtered = self.filters[channel].process(*sample) as f64;
+                self.segment_power[channel] += filtered * filtered;
+            }
+
+            self.segment_pos += 1;
+            if self.segment_pos == self.segment_len {
+                let power = self
+                    .segment_power
+                    .iter()
+                    .zip(&self.weights)
+                    .map(|(power, weight)| weight * power / self.segment_len as f64)
+                    .sum();
+                self.segments.push(power);
+                self.segment_power.fill(0.0);
+                self.segment_pos = 0;
+            }
+        }
+    }
+
+    pub fn loudness(&self) -> Loudness {
+        Loudness {
+            integrated_lufs: self.integrated_loudness(),
+            range_lu: self.loudness_range(),
+            true_peak: self.true_peak as f64,
+        }
+    }
+
+    fn integrated_loudness(&self) -> f64 {
+        let blocks: Vec<_> = self
+            .blocks(MOMENTARY_SEGMENTS)
+            .filter(|power| power_to_lufs(*power) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return f64::NEG_INFINITY;
+        }
+        let relative_gate = power_to_lufs(mean(&blocks)) + INTEGRATED_RELATIVE_GATE_LU;
+        let gated: Vec<_> = blocks
+            .into_iter()
+            .filter(|power| power_to_lufs(*power) > relative_gate)
+            .collect();
+        if gated.is_empty() {
+            return f64::NEG_INFINITY;
+        }
+        power_to_lufs(mean(&gated))
+    }
+
+    fn loudness_range(&self) -> f64 {
+        let blocks: Vec<_> = self
+            .blocks(SHORT_TERM_SEGMENTS)
+            .filter(|power| power_to_lufs(*power) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return 0.0;
+        }
+        let relative_gate = power_to_lufs(mean(&blocks)) + RANGE_RELATIVE_GATE_LU;
+        let mut gated: Vec<_> = blocks
+            .into_iter()
+            .map(power_to_lufs)
+            .filter(|loudness| *loudness > relative_gate)
+            .collect();
+        if gated.is_empty() {
+            return 0.0;
+        }
+        gated.sort_by(f64::total_cmp);
+        let percentile = |p: f64| gated[((gated.len() - 1) as f64 * p).round() as usize];
+        percentile(RANGE_HIGH_PERCENTILE) - percentile(RANGE_LOW_PERCENTILE)
+    }
+
+    // Mean power of each overlapping block of the given number of segments
+    fn blocks(&self, block_segments: usize) -> impl Iterator<Item = f64> + '_ {
+        self.segments
+            .windows(block_segments)
+            .map(move |window| window.iter().sum::<f64>() / block_segments as f64)
+    }
+}
+
+fn channel_weight(speaker: Speaker) -> f64 {
+    match speaker {
+        Speaker::Lfe => 0.0,
+        Speaker::RearLeft | Speaker::RearRight | Speaker::SideLeft | Speaker::SideRight => 1.41,
+        _ => 1.0,
+    }
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// The BS.1770 pre-filter and RLB high-pass filter, calculated for any sample rate
+#[derive(Clone, Copy, Debug)]
+struct KWeightingFilter {
+    shelf: BiquadCoefficients,
+    high_pass: BiquadCoefficients,
+    shelf_state: BiquadState,
+    high_pass_state: BiquadState,
+}
+
+impl KWeightingFilter {
+    fn new(sample_rate: f64) -> Self {
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = BiquadCoefficients::from_normalized(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = BiquadCoefficients::from_normalized(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        Self {
+            shelf,
+            high_pass,
+            shelf_state: BiquadState::default(),
+            high_pass_state: BiquadState::default(),
+        }
+    }
+
+    fn process(&mut self, sample: f32) -> f32 {
+        let shelved = self.shelf_state.process(&self.shelf, sample);
+        self.high_pass_state.process(&self.high_pass, shelved)
+    }
+}
+
+#[cfg(test)]
+#[path = "./loudness_test.rs"]
+mod loudness_test;
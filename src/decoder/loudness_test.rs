@@ -0,0 +1,68 @@
+use std::f32::consts::PI;
+
+use super::LoudnessAnalyzer;
+use crate::decoder::ChannelLayout;
+
+const SAMPLE_RATE: usize = 48000;
+
+fn sine(amplitude_db: f32, seconds: usize, channels: usize) -> Vec<f32> {
+    let amplitude = 10f32.powf(amplitude_db / 20.0);
+    (0..SAMPLE_RATE * seconds)
+        .flat_map(|i| {
+            let sample = amplitude * (2.0 * PI * 1000.0 * i as f32 / SAMPLE_RATE as f32).sin();
+            std::iter::repeat(sample).take(channels)
+        })
+        .collect()
+}
+
+#[test]
+fn stereo_sine() {
+    // EBU Tech 3341 test case 1
+    let mut analyzer = LoudnessAnalyzer::new(SAMPLE_RATE, &ChannelLayout::from_count(2));
+    analyzer.process(&sine(-23.0, 5, 2));
+    let loudness = analyzer.loudness();
+
+    assert!((loudness.integrated_lufs + 23.0).abs() < 0.1);
+    assert!(loudness.range_lu < 0.1);
+    assert!((loudness.true_peak_dbtp() + 23.0).abs() < 0.1);
+}
+
+#[test]
+fn relative_gate() {
+    // Quiet passages more than 10 LU below the average shouldn't lower the integrated loudness
+    let mut analyzer = LoudnessAnalyzer::new(SAMPLE_RATE, &ChannelLayout::from_count(2));
+    analyzer.process(&sine(-20.0, 10, 2));
+    analyzer.process(&sine(-50.0, 10, 2));
+    let loudness = analyzer.loudness();
+
+    assert!((loudness.integrated_lufs + 20.0).abs() < 0.2);
+}
+
+#[test]
+fn loudness_range() {
+    // EBU Tech 3342 test case 1
+    let mut analyzer = LoudnessAnalyzer::new(SAMPLE_RATE, &ChannelLayout::from_count(2));
+    analyzer.process(&sine(-20.0, 20, 2));
+    analyzer.process(&sine(-30.0, 20, 2));
+
+    assert!((analyzer.loudness().range_lu - 10.0).abs() < 1.0);
+}
+
+#[test]
+fn silence() {
+    let mut analyzer = LoudnessAnalyzer::new(SAMPLE_RATE, &ChannelLayout::from_count(1));
+    analyzer.process(&vec![0.0; SAMPLE_RATE]);
+    let loudness = analyzer.loudness();
+
+    assert_eq!(f64::NEG_INFINITY, loudness.integrated_lufs);
+    assert_eq!(None, loudness.replay_gain().track_gain_db);
+}
+
+#[test]
+fn replay_gain() {
+    let mut analyzer = LoudnessAnalyzer::new(SAMPLE_RATE, &ChannelLayout::from_count(2));
+    analyzer.process(&sine(-23.0, 5, 2));
+    let gain = analyzer.loudness().replay_gain().track_gain_db.unwrap();
+
+    assert!((gain - 5.0).abs() < 0.1);
+}
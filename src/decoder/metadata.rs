@@ -0,0 +1,390 @@
+use symphonia::core::meta::{
+    Metadata, MetadataRevision, StandardTag, StandardVisualKey, Tag as SymphoniaTag, Visual,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TagKey {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Genre,
+    Composer,
+    Date,
+    TrackNumber,
+    TrackTotal,
+    DiscNumber,
+    DiscTotal,
+    Comment,
+    Lyrics,
+    Copyright,
+    Label,
+    Encoder,
+    Isrc,
+    Bpm,
+    Language,
+    ReplayGainTrackGain,
+    ReplayGainTrackPeak,
+    ReplayGainAlbumGain,
+    ReplayGainAlbumPeak,
+    R128TrackGain,
+    R128AlbumGain,
+}
+
+impl TagKey {
+    /// Maps a raw tag key from any of the supported container formats to a standard key.
+    /// Matching is case-insensitive and ignores the prefixes used for user-defined ID3v2 and
+    /// iTunes tags.
+    pub fn from_raw(key: &str) -> Option<Self> {
+        let key = normalize_key(key);
+        let std_key = match key.as_str() {
+            "title" | "tit2" | "tt2" | "©nam" | "inam" | "name" => Self::Title,
+            "artist" | "tpe1" | "tp1" | "©art" | "iart" => Self::Artist,
+            "album" | "talb" | "tal" | "©alb" | "iprd" => Self::Album,
+            "albumartist" | "album artist" | "album_artist" | "tpe2" | "tp2" | "aart" => {
+                Self::AlbumArtist
+            }
+            "genre" | "tcon" | "tco" | "©gen" | "gnre" | "ignr" => Self::Genre,
+            "composer" | "tcom" | "tcm" | "©wrt" => Self::Composer,
+            "date" | "year" | "tdrc" | "tyer" | "tye" | "©day" | "icrd" => Self::Date,
+            "tracknumber" | "track" | "trck" | "trk" | "trkn" | "iprt" => Self::TrackNumber,
+            "tracktotal" | "totaltracks" => Self::TrackTotal,
+            "discnumber" | "disc" | "tpos" | "tpa" | "disk" => Self::DiscNumber,
+            "disctotal" | "totaldiscs" => Self::DiscTotal,
+            "comment" | "comm" | "com" | "©cmt" | "icmt" | "description" => Self::Comment,
+            "lyrics" | "unsyncedlyrics" | "uslt" | "ult" | "©lyr" => Self::Lyrics,
+            "copyright" | "tcop" | "tcr" | "cprt" | "icop" => Self::Copyright,
+            "label" | "publisher" | "organization" | "tpub" | "tpb" => Self::Label,
+            "encoder" | "encodedby" | "tsse" | "tss" | "©too" | "isft" => Self::Encoder,
+            "isrc" | "tsrc" => Self::Isrc,
+            "bpm" | "tbpm" | "tbp" | "tmpo" => Self::Bpm,
+            "language" | "tlan" => Self::Language,
+            "replaygain_track_gain" => Self::ReplayGainTrackGain,
+            "replaygain_track_peak" => Self::ReplayGainTrackPeak,
+            "replaygain_album_gain" => Self::ReplayGainAlbumGain,
+            "replaygain_album_peak" => Self::ReplayGainAlbumPeak,
+            "r128_track_gain" => Self::R128TrackGain,
+            "r128_album_gain" => Self::R128AlbumGain,
+            _ => return None,
+        };
+        Some(std_key)
+    }
+
+    // Symphonia has already parsed the value of standard tags, which keeps values such as MP4
+    // track and disc number pairs that are lost when the raw value is converted to a string
+    fn from_std(tag: &StandardTag) -> Option<(Self, String)> {
+        let (key, value) = match tag {
+            StandardTag::TrackTitle(v) => (Self::Title, v.to_string()),
+            StandardTag::Artist(v) => (Self::Artist, v.to_string()),
+            StandardTag::Album(v) => (Self::Album, v.to_string()),
+            StandardTag::AlbumArtist(v) => (Self::AlbumArtist, v.to_string()),
+            StandardTag::Genre(v) => (Self::Genre, v.to_string()),
+            StandardTag::Composer(v) => (Self::Composer, v.to_string()),
+            StandardTag::RecordingDate(v) | StandardTag::ReleaseDate(v) => {
+                (Self::Date, v.to_string())
+            }
+            StandardTag::TrackNumber(v) => (Self::TrackNumber, v.to_string()),
+            StandardTag::TrackTotal(v) => (Self::TrackTotal, v.to_string()),
+            StandardTag::DiscNumber(v) => (Self::DiscNumber, v.to_string()),
+            StandardTag::DiscTotal(v) => (Self::DiscTotal, v.to_string()),
+            StandardTag::Comment(v) => (Self::Comment, v.to_string()),
+            StandardTag::Lyrics(v) => (Self::Lyrics, v.to_string()),
+            StandardTag::Copyright(v) => (Self::Copyright, v.to_string()),
+            StandardTag::Label(v) => (Self::Label, v.to_string()),
+            StandardTag::EncodedBy(v) | StandardTag::Encoder(v) => (Self::Encoder, v.to_string()),
+            StandardTag::IdentIsrc(v) => (Self::Isrc, v.to_string()),
+            StandardTag::Bpm(v) => (Self::Bpm, v.to_string()),
+            StandardTag::Language(v) => (Self::Language, v.to_string()),
+            StandardTag::ReplayGainTrackGain(v) => (Self::ReplayGainTrackGain, v.to_string()),
+            StandardTag::ReplayGainTrackPeak(v) => (Self::ReplayGainTrackPeak, v.to_string()),
+            StandardTag::ReplayGainAlbumGain(v) => (Self::ReplayGainAlbumGain, v.to_string()),
+            StandardTag::ReplayGainAlbumPeak(v) => (Self::ReplayGainAlbumPeak, v.to_string()),
+            _ => return None,
+        };
+        Some((key, value))
+    }
+}
+
+// Prefixes used by user-defined ID3v2 frames and iTunes freeform atoms
+const KEY_PREFIXES: [&str; 4] = [
+    "txxx:",
+    "----:com.apple.itunes:",
+    "com.apple.itunes:",
+    "----:",
+];
+
+fn normalize_key(key: &str) -> String {
+    let key = key.trim().to_lowercase();
+    KEY_PREFIXES
+        .iter()
+        .find_map(|prefix| key.strip_prefix(prefix))
+        .map(|key| key.to_owned())
+        .unwrap_or(key)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tag {
+    pub key: String,
+    pub value: String,
+    pub std_key: Option<TagKey>,
+}
+
+impl Tag {
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        Self {
+            std_key: TagKey::from_raw(&key),
+            key,
+            value: value.into(),
+        }
+    }
+
+    // Uses the standard tag when symphonia recognized one, otherwise the raw key is matched
+    // against the keys it doesn't map
+    fn from_symphonia(tag: &SymphoniaTag) -> Self {
+        let key = tag.raw.key.to_string();
+        match tag.std.as_ref().and_then(TagKey::from_std) {
+            Some((std_key, value)) => Self {
+                key,
+                value,
+                std_key: Some(std_key),
+            },
+            None => Self::new(key, tag.raw.value.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PictureUsage {
+    FileIcon,
+    OtherIcon,
+    FrontCover,
+    BackCover,
+    Leaflet,
+    Media,
+    LeadArtist,
+    Artist,
+    Conductor,
+    Band,
+    Composer,
+    Lyricist,
+    RecordingLocation,
+    RecordingSession,
+    Performance,
+    ScreenCapture,
+    Illustration,
+    BandLogo,
+    PublisherLogo,
+    Other,
+}
+
+impl From<StandardVisualKey> for PictureUsage {
+    fn from(key: StandardVisualKey) -> Self {
+        match key {
+            StandardVisualKey::FileIcon => Self::FileIcon,
+            StandardVisualKey::OtherIcon => Self::OtherIcon,
+            StandardVisualKey::FrontCover => Self::FrontCover,
+            StandardVisualKey::BackCover => Self::BackCover,
+            StandardVisualKey::Leaflet => Self::Leaflet,
+            StandardVisualKey::Media => Self::Media,
+            StandardVisualKey::LeadArtistPerformerSoloist => Self::LeadArtist,
+            StandardVisualKey::ArtistPerformer => Self::Artist,
+            StandardVisualKey::Conductor => Self::Conductor,
+            StandardVisualKey::BandOrchestra => Self::Band,
+            StandardVisualKey::Composer => Self::Composer,
+            StandardVisualKey::Lyricist => Self::Lyricist,
+            StandardVisualKey::RecordingLocation => Self::RecordingLocation,
+            StandardVisualKey::RecordingSession => Self::RecordingSession,
+            StandardVisualKey::Performance => Self::Performance,
+            StandardVisualKey::ScreenCapture => Self::ScreenCapture,
+            StandardVisualKey::Illustration => Self::Illustration,
+            StandardVisualKey::BandArtistLogo => Self::BandLogo,
+            StandardVisualKey::PublisherStudioLogo => Self::PublisherLogo,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Picture {
+    pub data: Box<[u8]>,
+    pub media_type: Option<String>,
+    pub usage: PictureUsage,
+}
+
+impl Picture {
+    fn from_visual(visual: &Visual) -> Self {
+        Self {
+            media_type: visual
+                .media_type
+                .clone()
+                .or_else(|| sniff_media_type(&visual.data).map(|t| t.to_owned())),
+            usage: visual.usage.map(Into::into).unwrap_or(PictureUsage::Other),
+            data: visual.data.clone(),
+        }
+    }
+}
+
+// Some containers don't declare the image format, so fall back to checking the magic bytes
+fn sniff_media_type(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [
+            b'R',
+            b'I',
+            b'F',
+            b'F',
+            _,
+            _,
+            _,
+            _,
+            b'W',
+            b'E',
+            b'B',
+            b'P',
+            ..,
+        ] => Some("image/webp"),
+        [b'B', b'M', ..] => Some("image/bmp"),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrackMetadata {
+    tags: Vec<Tag>,
+    pictures: Vec<Picture>,
+}
+
+impl TrackMetadata {
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    pub fn pictures(&self) -> &[Picture] {
+        &self.pictures
+    }
+
+    /// The front cover if one is tagged, otherwise the first picture.
+    pub fn cover(&self) -> Option<&Picture> {
+        self.pictures
+            .iter()
+            .find(|p| p.usage == PictureUsage::FrontCover)
+            .or(self.pictures.first())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.pictures.is_empty()
+    }
+
+    pub fn get(&self, key: TagKey) -> Option<&str> {
+        self.get_all(key).next()
+    }
+
+    pub fn get_all(&self, key: TagKey) -> impl Iterator<Item = &str> + '_ {
+        self.tags
+            .iter()
+            .filter(move |t| t.std_key == Some(key))
+            .map(|t| t.value.as_str())
+    }
+
+    /// Looks up a tag by its raw key, including vendor-specific tags that have no
+    /// [`TagKey`] equivalent.
+    pub fn get_raw(&self, key: &str) -> Option<&str> {
+        let key = normalize_key(key);
+        self.tags
+            .iter()
+            .find(|t| normalize_key(&t.key) == key)
+            .map(|t| t.value.as_str())
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.get(TagKey::Title)
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.get(TagKey::Artist)
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.get(TagKey::Album)
+    }
+
+    pub fn album_artist(&self) -> Option<&str> {
+        self.get(TagKey::AlbumArtist)
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.get(TagKey::Genre)
+    }
+
+    pub fn date(&self) -> Option<&str> {
+        self.get(TagKey::Date)
+    }
+
+    pub fn track_number(&self) -> Option<u32> {
+        self.number_and_total(TagKey::TrackNumber).0
+    }
+
+    pub fn track_total(&self) -> Option<u32> {
+        parse_number(self.get(TagKey::TrackTotal))
+            .or_else(|| self.number_and_total(TagKey::TrackNumber).1)
+    }
+
+    pub fn disc_number(&self) -> Option<u32> {
+        self.number_and_total(TagKey::DiscNumber).0
+    }
+
+    pub fn disc_total(&self) -> Option<u32> {
+        parse_number(self.get(TagKey::DiscTotal))
+            .or_else(|| self.number_and_total(TagKey::DiscNumber).1)
+    }
+
+    // Number tags are commonly stored as "1/12"
+    fn number_and_total(&self, key: TagKey) -> (Option<u32>, Option<u32>) {
+        match self.get(key).map(|v| v.split_once('/')) {
+            Some(Some((number, total))) => (parse_number(Some(number)), parse_number(Some(total))),
+            Some(None) => (parse_number(self.get(key)), None),
+            None => (None, None),
+        }
+    }
+
+    /// Merges a new set of tags. Tags from later revisions replace earlier ones with the same
+    /// key.
+    pub(crate) fn apply_tags(&mut self, tags: Vec<Tag>) {
+        self.tags.retain(|existing| {
+            let key = normalize_key(&existing.key);
+            !tags.iter().any(|t| normalize_key(&t.key) == key)
+        });
+        self.tags.extend(tags);
+    }
+
+    pub(crate) fn apply_revision(&mut self, revision: &MetadataRevision) {
+        self.apply_tags(revision.tags().iter().map(Tag::from_symphonia).collect());
+        if !revision.visuals().is_empty() {
+            self.pictures = revision
+                .visuals()
+                .iter()
+                .map(Picture::from_visual)
+                .collect();
+        }
+    }
+
+    pub(crate) fn apply_metadata(&mut self, metadata: &mut Metadata<'_>) {
+        // Apply revisions oldest first so newer values take precedence
+        while let Some(revision) = metadata.pop() {
+            self.apply_revision(&revision);
+        }
+        if let Some(revision) = metadata.current() {
+            self.apply_revision(revision);
+        }
+    }
+}
+
+fn parse_number(value: Option<&str>) -> Option<u32> {
+    value.and_then(|v| v.trim().parse().ok())
+}
+
+#[cfg(test)]
+#[path = "./metadata_test.rs"]
+mod metadata_test;
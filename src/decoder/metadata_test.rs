@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use super::{Tag, TagKey, TrackMetadata, sniff_media_type};
+use crate::decoder::{ReadSeekSource, probe_metadata};
+
+#[test]
+fn standard_keys() {
+    assert_eq!(Some(TagKey::Title), TagKey::from_raw("TIT2"));
+    assert_eq!(Some(TagKey::Title), TagKey::from_raw("title"));
+    assert_eq!(Some(TagKey::Artist), TagKey::from_raw("©ART"));
+    assert_eq!(Some(TagKey::AlbumArtist), TagKey::from_raw("ALBUM ARTIST"));
+    assert_eq!(Some(TagKey::Label), TagKey::from_raw("TXXX:LABEL"));
+    assert_eq!(None, TagKey::from_raw("CUSTOM_TAG"));
+}
+
+#[test]
+fn track_and_disc_numbers() {
+    let mut metadata = TrackMetadata::default();
+    metadata.apply_tags(vec![Tag::new("TRCK", "3/12"), Tag::new("DISCNUMBER", "2")]);
+
+    assert_eq!(Some(3), metadata.track_number());
+    assert_eq!(Some(12), metadata.track_total());
+    assert_eq!(Some(2), metadata.disc_number());
+    assert_eq!(None, metadata.disc_total());
+}
+
+#[test]
+fn mp4_track_and_disc_numbers() {
+    let source = ReadSeekSource::from_path(Path::new("examples/tags.m4a"));
+    let metadata = probe_metadata(Box::new(source)).unwrap();
+
+    assert_eq!(Some("Tagged Tone"), metadata.title());
+    assert_eq!(Some(3), metadata.track_number());
+    assert_eq!(Some(12), metadata.track_total());
+    assert_eq!(Some(1), metadata.disc_number());
+    assert_eq!(Some(2), metadata.disc_total());
+}
+
+#[test]
+fn later_revisions_replace_tags() {
+    let mut metadata = TrackMetadata::default();
+    metadata.apply_tags(vec![
+        Tag::new("TITLE", "First"),
+        Tag::new("ARTIST", "A"),
+        Tag::new("ARTIST", "B"),
+    ]);
+    metadata.apply_tags(vec![Tag::new("title", "Second")]);
+
+    assert_eq!(Some("Second"), metadata.title());
+    assert_eq!(
+        vec!["A", "B"],
+        metadata.get_all(TagKey::Artist).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn raw_vendor_tags() {
+    let mut metadata = TrackMetadata::default();
+    metadata.apply_tags(vec![Tag::new("----:com.apple.iTunes:MOOD", "Calm")]);
+
+    assert_eq!(Some("Calm"), metadata.get_raw("mood"));
+}
+
+#[test]
+fn sniff_picture_type() {
+    assert_eq!(
+        Some("image/jpeg"),
+        sniff_media_type(&[0xFF, 0xD8, 0xFF, 0xE0])
+    );
+    assert_eq!(Some("image/png"), sniff_media_type(b"\x89PNG\r\n\x1a\n"));
+    assert_eq!(None, sniff_media_type(&[0x00, 0x01]));
+}
@@ -1,6 +1,7 @@
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use dasp::sample::Sample as DaspSample;
+use symphonia::core::audio::Channels;
 use symphonia::core::audio::conv::ConvertibleSample;
 use symphonia::core::audio::sample::Sample;
 use symphonia::core::codecs::CodecParameters;
@@ -9,7 +10,7 @@ use symphonia::core::errors::Error;
 pub use symphonia::core::formats::SeekTo;
 use symphonia::core::formats::probe::Hint;
 use symphonia::core::formats::{
-    FormatOptions, FormatReader, Packet, SeekMode, SeekedTo, TrackType,
+    FormatOptions, FormatReader, Packet, SeekMode, SeekedTo, Track, TrackType,
 };
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
@@ -22,9 +23,21 @@ use tracing::{error, info, warn};
 mod resampler;
 pub use resampler::*;
 mod channel_buffer;
+mod channel_mixer;
+pub use channel_mixer::*;
+mod loudness;
+pub use loudness::*;
+mod metadata;
+pub use metadata::*;
+mod replay_gain;
+pub use replay_gain::*;
 mod source;
 pub use source::*;
+mod track;
+pub use track::*;
 mod vec_ext;
+mod volume;
+pub use volume::*;
 
 #[derive(Error, Debug)]
 pub enum DecoderError {
@@ -44,11 +57,17 @@ pub enum DecoderError {
     ResetRequired,
     #[error("Only audio tracks are supported")]
     InvalidTrackType,
+    #[error("No track was found with id {0}")]
+    TrackNotFound(u32),
 }
 
 #[derive(Error, Debug)]
-#[error("Error seeking: {0}")]
-pub struct SeekError(#[from] symphonia::core::errors::Error);
+pub enum SeekError {
+    #[error("Error seeking: {0}")]
+    SeekFailed(#[from] symphonia::core::errors::Error),
+    #[error("The sample rate isn't known until audio has been decoded")]
+    UnknownSampleRate,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CurrentPosition {
@@ -56,11 +75,71 @@ pub struct CurrentPosition {
     pub retrieval_time: Option<Duration>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackDuration {
+    pub duration: Duration,
+    /// Set when the container doesn't declare its length and the duration was estimated from
+    /// the codec's declared bitrate or the average bitrate seen so far instead.
+    pub is_estimated: bool,
+}
+
 const NANOS_PER_SEC: f64 = 1_000_000_000.0;
+// Roughly one step of a 16 bit sample. Lossy decoders rarely output exact zeros for padding.
+const SILENCE_THRESHOLD: f32 = 1.0 / 32768.0;
+// Silence is only held back up to this length so a quiet passage in the middle of a track doesn't
+// stall playback and come out as one large buffer
+const MAX_TRAILING_SILENCE: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SeekAccuracy {
+    /// Seeks to the start of the packet containing the requested time.
+    #[default]
+    Coarse,
+    /// Seeks to the exact requested sample. This is slower since any packets leading up to the
+    /// requested time need to be decoded.
+    Accurate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekDirection {
+    Forward,
+    Backward,
+}
 
-#[derive(Clone, Debug, Default)]
+pub(crate) const DEFAULT_VOLUME_RAMP: Duration = Duration::from_millis(50);
+const DEFAULT_PAUSE_FADE: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Debug)]
 pub struct DecoderSettings {
     pub enable_gapless: bool,
+    pub track_id: Option<u32>,
+    pub seek_accuracy: SeekAccuracy,
+    pub downmix: DownmixMode,
+    pub upmix: UpmixMode,
+    /// Removes up to two seconds of silence at the end of the track. This is useful for files
+    /// that don't declare their encoder padding. Silence in the middle of the track is preserved.
+    pub trim_trailing_silence: bool,
+    pub replay_gain: ReplayGainSettings,
+    /// How long it takes to fade to a new volume after calling [`Decoder::set_volume`].
+    pub volume_ramp: Duration,
+    /// How long it takes to fade out when pausing and fade back in when resuming.
+    pub pause_fade: Duration,
+}
+
+impl Default for DecoderSettings {
+    fn default() -> Self {
+        Self {
+            enable_gapless: false,
+            track_id: None,
+            seek_accuracy: SeekAccuracy::default(),
+            downmix: DownmixMode::default(),
+            upmix: UpmixMode::default(),
+            trim_trailing_silence: false,
+            replay_gain: ReplayGainSettings::default(),
+            volume_ramp: DEFAULT_VOLUME_RAMP,
+            pause_fade: DEFAULT_PAUSE_FADE,
+        }
+    }
 }
 
 pub struct Decoder<T: Sample + dasp::sample::Sample> {
@@ -70,17 +149,58 @@ pub struct Decoder<T: Sample + dasp::sample::Sample> {
     reader: Box<dyn FormatReader>,
     time_base: TimeBase,
     buf_len: usize,
-    volume: T::Float,
+    volume: VolumeRamp,
+    pause_fade: VolumeRamp,
+    // The part of the packet after the pause fade ended, which is played when resuming
+    paused_samples: Vec<T>,
+    replay_gain: T::Float,
+    replay_gain_override: Option<ReplayGain>,
     track_id: u32,
     input_channels: usize,
+    input_layout: ChannelLayout,
     output_channels: usize,
+    channel_mixer: ChannelMixer<T>,
     timestamp: u64,
     is_paused: bool,
     sample_rate: usize,
-    seek_required_ts: Option<u64>,
+    // The first packet after a coarse seek, which is read straight away so the position reported
+    // is where playback actually resumes
+    seek_packet: Option<Packet>,
+    seek_trim_ts: Option<u64>,
+    pending_silence_frames: usize,
+    metadata: TrackMetadata,
+    num_frames: Option<u64>,
+    byte_len: Option<u64>,
+    bitrate: Option<u64>,
+    decoded_bytes: u64,
+    decoded_ts: u64,
     settings: DecoderSettings,
 }
 
+/// Reads the tags and pictures from a source without creating a decoder.
+pub fn probe_metadata(source: Box<dyn Source>) -> Result<TrackMetadata, DecoderError> {
+    let mut reader = probe(source, FormatOptions::default())?;
+    let mut metadata = TrackMetadata::default();
+    metadata.apply_metadata(&mut reader.metadata());
+    Ok(metadata)
+}
+
+fn probe(
+    source: Box<dyn Source>,
+    format_opts: FormatOptions,
+) -> Result<Box<dyn FormatReader>, DecoderError> {
+    let mut hint = Hint::new();
+    if let Some(extension) = source.get_file_ext() {
+        hint.with_extension(&extension);
+    }
+    let mss = MediaSourceStream::new(source.as_media_source(), Default::default());
+    let metadata_opts = MetadataOptions::default();
+
+    symphonia::default::get_probe()
+        .probe(&hint, mss, format_opts, metadata_opts)
+        .map_err(DecoderError::FormatNotFound)
+}
+
 impl<T> Decoder<T>
 where
     T: Sample + dasp::sample::Sample + ConvertibleSample,
@@ -91,43 +211,25 @@ where
         output_channels: usize,
         settings: DecoderSettings,
     ) -> Result<Self, DecoderError> {
-        let mut hint = Hint::new();
-        if let Some(extension) = source.get_file_ext() {
-            hint.with_extension(&extension);
-        }
-        let mss = MediaSourceStream::new(source.as_media_source(), Default::default());
-
         let format_opts = FormatOptions {
             enable_gapless: settings.enable_gapless,
             ..FormatOptions::default()
         };
-        let metadata_opts = MetadataOptions::default();
-
-        let reader =
-            match symphonia::default::get_probe().probe(&hint, mss, format_opts, metadata_opts) {
-                Ok(probed) => probed,
-                Err(e) => return Err(DecoderError::FormatNotFound(e)),
-            };
-
-        let track = match reader.default_track(TrackType::Audio) {
-            Some(track) => track.to_owned(),
-            None => return Err(DecoderError::NoTracks),
-        };
-
-        // If no time base found, default to a dummy one
-        // and attempt to calculate it from the sample rate later
-        let time_base = track.time_base.unwrap_or_else(|| TimeBase::new(1, 1));
-
-        let decode_opts = AudioDecoderOptions { verify: true };
-        let Some(CodecParameters::Audio(codec_params)) = track.codec_params else {
-            return Err(DecoderError::InvalidTrackType);
-        };
-        let symphonia_decoder = match symphonia::default::get_codecs()
-            .make_audio_decoder(&codec_params, &decode_opts)
-        {
-            Ok(decoder) => decoder,
-            Err(e) => return Err(DecoderError::UnsupportedCodec(e)),
-        };
+        let byte_len = source.byte_len();
+        let reader = probe(source, format_opts)?;
+
+        let track = match settings.track_id {
+            Some(track_id) => reader
+                .tracks()
+                .iter()
+                .find(|t| t.id == track_id)
+                .ok_or(DecoderError::TrackNotFound(track_id))?,
+            None => reader
+                .default_track(TrackType::Audio)
+                .ok_or(DecoderError::NoTracks)?,
+        }
+        .to_owned();
+        let (symphonia_decoder, time_base) = Self::create_audio_decoder(&track)?;
 
         let mut decoder = Self {
             decoder: symphonia_decoder,
@@ -135,32 +237,156 @@ where
             time_base,
             buf_len: 0,
             input_channels: 0,
+            input_layout: ChannelLayout::from_count(output_channels),
             output_channels,
+            channel_mixer: ChannelMixer::new(&ChannelMatrix::identity(output_channels)),
             track_id: track.id,
             buf: vec![],
             sample_buf: vec![],
-            volume,
+            volume: VolumeRamp::new(volume.to_sample()),
+            pause_fade: VolumeRamp::new(1.0),
+            paused_samples: vec![],
+            replay_gain: T::IDENTITY,
+            replay_gain_override: None,
             timestamp: 0,
             is_paused: false,
             sample_rate: 0,
-            seek_required_ts: None,
+            seek_packet: None,
+            seek_trim_ts: None,
+            pending_silence_frames: 0,
+            metadata: TrackMetadata::default(),
+            num_frames: track.num_frames,
+            byte_len,
+            bitrate: track_bitrate(&track),
+            decoded_bytes: 0,
+            decoded_ts: 0,
             settings,
         };
+        decoder.update_metadata();
         decoder.initialize()?;
 
         Ok(decoder)
     }
 
+    pub fn tracks(&self) -> Vec<TrackInfo> {
+        self.reader
+            .tracks()
+            .iter()
+            .filter_map(TrackInfo::from_track)
+            .collect()
+    }
+
+    pub fn track_id(&self) -> u32 {
+        self.track_id
+    }
+
+    /// Switches to another track in the same file, continuing from the current position. The
+    /// new track may use a different sample rate or channel layout, so use
+    /// `AudioManager::select_track` instead when playing through an `AudioManager`.
+    pub fn select_track(&mut self, track_id: u32) -> Result<(), DecoderError> {
+        if track_id == self.track_id {
+            return Ok(());
+        }
+        let track = self
+            .reader
+            .tracks()
+            .iter()
+            .find(|t| t.id == track_id)
+            .ok_or(DecoderError::TrackNotFound(track_id))?
+            .to_owned();
+        self.switch_track(&track)
+    }
+
+    fn switch_track(&mut self, track: &Track) -> Result<(), DecoderError> {
+        let (decoder, time_base) = Self::create_audio_decoder(track)?;
+
+        let position = self.current_position();
+        self.decoder = decoder;
+        self.time_base = time_base;
+        self.track_id = track.id;
+        self.num_frames = track.num_frames;
+        self.bitrate = track_bitrate(track);
+        self.decoded_bytes = 0;
+        self.decoded_ts = 0;
+        // Trimming state from the previous track doesn't apply to the new one
+        self.seek_packet = None;
+        self.seek_trim_ts = None;
+        self.pending_silence_frames = 0;
+        // Otherwise any change is detected when the first packet is decoded
+        if let Some((sample_rate, channels)) = track_format(track) {
+            self.set_format(sample_rate, channels);
+        }
+
+        if let Err(e) = self.reader_seek(position.position, self.settings.seek_accuracy) {
+            warn!("Error seeking to previous position after switching tracks: {e:?}");
+        }
+        self.initialize()
+    }
+
+    pub fn metadata(&self) -> &TrackMetadata {
+        &self.metadata
+    }
+
     pub fn set_volume(&mut self, volume: T::Float) {
-        self.volume = volume;
+        self.volume.set_target(volume.to_sample());
     }
 
     pub fn volume(&self) -> T::Float {
-        self.volume
+        self.volume.target().to_sample()
+    }
+
+    pub fn set_volume_db(&mut self, volume_db: f32) {
+        self.volume.set_target(db_to_linear(volume_db));
     }
 
+    pub fn volume_db(&self) -> f32 {
+        linear_to_db(self.volume.target())
+    }
+
+    /// Sets the volume from a 0 to 1 scale using the given curve.
+    pub fn set_volume_scaled(&mut self, level: f32, curve: VolumeCurve) {
+        self.volume.set_target(curve.to_linear(level));
+    }
+
+    pub fn volume_scaled(&self, curve: VolumeCurve) -> f32 {
+        curve.from_linear(self.volume.target())
+    }
+
+    pub fn mute(&mut self) {
+        self.volume.set_muted(true);
+    }
+
+    pub fn unmute(&mut self) {
+        self.volume.set_muted(false);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.volume.is_muted()
+    }
+
+    pub fn replay_gain_settings(&self) -> &ReplayGainSettings {
+        &self.settings.replay_gain
+    }
+
+    pub fn set_replay_gain_settings(&mut self, settings: ReplayGainSettings) {
+        self.settings.replay_gain = settings;
+        self.update_replay_gain();
+    }
+
+    /// Uses the given gain values instead of the ones from the track's tags, such as the results
+    /// of [`analyze_loudness`]. Pass `None` to go back to using the tags.
+    pub fn set_replay_gain(&mut self, replay_gain: Option<ReplayGain>) {
+        self.replay_gain_override = replay_gain;
+        self.update_replay_gain();
+    }
+
+    /// Pauses after fading out. The decoder keeps producing audio until the fade is complete, and
+    /// the rest of the packet the fade ended in is played when resuming.
     pub fn pause(&mut self) {
-        self.is_paused = true;
+        if !self.is_paused {
+            self.is_paused = true;
+            self.pause_fade.set_target(0.0);
+        }
     }
 
     pub fn is_paused(&self) -> bool {
@@ -168,27 +394,118 @@ where
     }
 
     pub fn resume(&mut self) {
-        self.is_paused = false;
+        if self.is_paused {
+            self.is_paused = false;
+            self.pause_fade.set_target(1.0);
+        }
     }
 
     pub fn sample_rate(&self) -> usize {
         self.sample_rate
     }
 
-    pub fn seek(&mut self, time: Duration) -> Result<SeekedTo, SeekError> {
-        let position = self.current_position();
-        let seek_result = match self.reader_seek(time) {
-            Ok(result) => {
-                self.seek_required_ts = Some(result.required_ts);
-                Ok(result)
+    pub fn input_channels(&self) -> usize {
+        self.input_channels
+    }
+
+    pub fn input_layout(&self) -> &ChannelLayout {
+        &self.input_layout
+    }
+
+    pub fn output_channels(&self) -> usize {
+        self.output_channels
+    }
+
+    // The output channels that the source is mixed into, apart from the LFE
+    pub(crate) fn main_output_channels(&self) -> Vec<bool> {
+        let matrix = self.channel_matrix();
+        ChannelLayout::from_count(self.output_channels)
+            .speakers()
+            .iter()
+            .enumerate()
+            .map(|(o, speaker)| *speaker != Speaker::Lfe && matrix.row(o).iter().any(|c| *c != 0.0))
+            .collect()
+    }
+
+    pub fn set_output_channels(&mut self, output_channels: usize) {
+        if output_channels == self.output_channels {
+            return;
+        }
+        self.output_channels = output_channels;
+        self.channel_mixer = ChannelMixer::new(&self.channel_matrix());
+        // The audio held back by pausing was already mixed for the old channel count
+        self.paused_samples.clear();
+
+        // Remix the last decoded packet so the current buffer matches the new channel count
+        self.adjust_buffer_size(self.channel_mixer.output_len(self.sample_buf.len()));
+        self.channel_mixer.mix(
+            &self.sample_buf,
+            &mut self.buf[..self.buf_len],
+            self.replay_gain,
+        );
+        self.volume
+            .apply(&mut self.buf[..self.buf_len], self.output_channels);
+    }
+
+    pub fn seek(&mut self, time: Duration) -> Result<Duration, SeekError> {
+        self.seek_with_accuracy(time, self.settings.seek_accuracy)
+    }
+
+    /// Seeks relative to the current position, stopping at the start or end of the track. Seeking
+    /// to the end leaves only the last frame to play, so the decoder finishes straight away.
+    pub fn seek_by(
+        &mut self,
+        delta: Duration,
+        direction: SeekDirection,
+    ) -> Result<Duration, SeekError> {
+        let position = self.current_position().position;
+        let mut time = match direction {
+            SeekDirection::Forward => position.saturating_add(delta),
+            SeekDirection::Backward => position.saturating_sub(delta),
+        };
+        if let Some(duration) = self.duration() {
+            if time >= duration.duration && self.sample_rate > 0 {
+                // Seeking to the very end is out of range, so land on the last frame instead and
+                // let playback finish from there
+                let last_frame = duration
+                    .duration
+                    .saturating_sub(Duration::from_secs_f64(1.0 / self.sample_rate as f64));
+                return self.seek_with_accuracy(last_frame, SeekAccuracy::Accurate);
             }
+            time = time.min(duration.duration);
+        }
+        self.seek(time)
+    }
+
+    pub fn seek_to_timestamp(&mut self, timestamp: TimeStamp) -> Result<Duration, SeekError> {
+        self.seek(self.timestamp_to_duration(timestamp))
+    }
+
+    pub fn seek_to_frame(&mut self, frame: u64) -> Result<Duration, SeekError> {
+        if self.sample_rate == 0 {
+            return Err(SeekError::UnknownSampleRate);
+        }
+        self.seek(Duration::from_secs_f64(
+            frame as f64 / self.sample_rate as f64,
+        ))
+    }
+
+    /// Returns the position playback resumes from. With [`SeekAccuracy::Coarse`], this is the
+    /// start of the packet containing the requested time rather than the time itself.
+    pub fn seek_with_accuracy(
+        &mut self,
+        time: Duration,
+        accuracy: SeekAccuracy,
+    ) -> Result<Duration, SeekError> {
+        let position = self.current_position();
+        let seek_result = match self.reader_seek(time, accuracy) {
+            Ok(result) => Ok(result),
             Err(e) => {
                 // Seek was probably out of bounds
                 warn!("Error seeking: {e:?}. Resetting to previous position");
-                match self.reader_seek(position.position) {
+                match self.reader_seek(position.position, accuracy) {
                     Ok(seeked_to) => {
                         info!("Reset position to {seeked_to:?}");
-                        self.seek_required_ts = Some(seeked_to.required_ts);
                         // Reset succeeded, but send the original error back to the caller since the
                         // intended seek failed
                         Err(e)
@@ -203,9 +520,11 @@ where
 
         // Per the docs, decoders need to be reset after seeking
         self.decoder.reset();
-        Ok(seek_result?)
+        seek_result?;
+        Ok(self.current_position().position)
     }
 
+    /// The position in the media, which isn't affected by the playback speed.
     pub fn current_position(&self) -> CurrentPosition {
         let time = self.time_base.calc_time(self.timestamp);
         let millis = ((time.seconds as f64 + time.frac) * 1000.0) as u64;
@@ -225,55 +544,247 @@ where
         }
     }
 
-    fn reader_seek(&mut self, time: Duration) -> Result<SeekedTo, symphonia::core::errors::Error> {
+    pub fn duration(&self) -> Option<TrackDuration> {
+        if let Some(num_frames) = self.num_frames {
+            return Some(TrackDuration {
+                duration: self.timestamp_to_duration(num_frames),
+                is_estimated: false,
+            });
+        }
+        // Streams without a header don't declare their length, so estimate it from the bitrate
+        // declared by the codec, or the average bitrate seen so far for VBR streams
+        let byte_len = self.byte_len?;
+        let duration = match self.bitrate {
+            Some(bitrate) => Duration::from_secs_f64(byte_len as f64 * 8.0 / bitrate as f64),
+            None if self.decoded_bytes > 0 => {
+                let estimated_ts =
+                    (byte_len as f64 * self.decoded_ts as f64 / self.decoded_bytes as f64) as u64;
+                self.timestamp_to_duration(estimated_ts)
+            }
+            None => return None,
+        };
+        Some(TrackDuration {
+            duration,
+            is_estimated: true,
+        })
+    }
+
+    /// The fraction of the track that has been played, from 0 to 1.
+    pub fn progress(&self) -> Option<f64> {
+        let duration = self.duration()?.duration.as_secs_f64();
+        if duration == 0.0 {
+            return None;
+        }
+        let position = self.current_position().position.as_secs_f64();
+        Some((position / duration).clamp(0.0, 1.0))
+    }
+
+    fn timestamp_to_duration(&self, timestamp: u64) -> Duration {
+        let time = self.time_base.calc_time(timestamp);
+        Duration::from_secs_f64(time.seconds as f64 + time.frac)
+    }
+
+    fn reader_seek(
+        &mut self,
+        time: Duration,
+        accuracy: SeekAccuracy,
+    ) -> Result<SeekedTo, symphonia::core::errors::Error> {
         let seek_time = Time::new(time.as_secs(), time.subsec_nanos() as f64 / NANOS_PER_SEC);
-        let res = self.reader.seek(SeekMode::Coarse, SeekTo::Time {
+        let mode = match accuracy {
+            SeekAccuracy::Coarse => SeekMode::Coarse,
+            SeekAccuracy::Accurate => SeekMode::Accurate,
+        };
+        let seeked_to = self.reader.seek(mode, SeekTo::Time {
             time: seek_time,
             track_id: Some(self.track_id),
-        });
-        if res.is_ok() {
-            // Manually set the timestamp here in case it's queried before we decode the next packet
-            self.timestamp = self.time_base.calc_timestamp(seek_time);
+        })?;
+        self.paused_samples.clear();
+        self.pending_silence_frames = 0;
+        self.seek_packet = None;
+        // Manually set the timestamp here in case it's queried before we decode the next packet
+        self.timestamp = seeked_to.actual_ts.max(seeked_to.required_ts);
+        match accuracy {
+            SeekAccuracy::Coarse => {
+                self.seek_trim_ts = None;
+                self.seek_packet = self.read_packet_at(seeked_to.required_ts)?;
+                if let Some(packet) = &self.seek_packet {
+                    self.timestamp = packet.ts();
+                }
+            }
+            SeekAccuracy::Accurate => {
+                // Packets before the requested time still need to be decoded to prime the
+                // decoder, so they're trimmed after decoding instead of being skipped
+                self.seek_trim_ts = Some(seeked_to.required_ts);
+            }
         }
-        res
+        Ok(seeked_to)
     }
 
-    fn initialize(&mut self) -> Result<(), DecoderError> {
-        let mut samples_skipped = 0;
-
-        loop {
-            self.next()?;
-            if self.time_base.denom == 1 {
-                self.time_base = TimeBase::new(1, self.sample_rate as u32);
+    // Skips to the packet of the current track that contains the timestamp
+    fn read_packet_at(&mut self, timestamp: u64) -> Result<Option<Packet>, Error> {
+        while let Some(packet) = self.reader.next_packet()? {
+            if packet.track_id() == self.track_id && packet.ts() + packet.dur() > timestamp {
+                return Ok(Some(packet));
             }
-            if !self.settings.enable_gapless {
-                break;
+        }
+        Ok(None)
+    }
+
+    // Returns false if the entire decoded buffer comes before the seek target
+    fn trim_to_seek_target(&mut self, packet_ts: u64) -> bool {
+        let Some(required_ts) = self.seek_trim_ts else {
+            return true;
+        };
+        let frames = self.buf_len / self.output_channels;
+        let skip_frames = self.timestamp_to_frames(required_ts.saturating_sub(packet_ts));
+        if skip_frames >= frames {
+            return false;
+        }
+
+        let skip_samples = skip_frames * self.output_channels;
+        self.buf.copy_within(skip_samples..self.buf_len, 0);
+        self.buf_len -= skip_samples;
+        self.seek_trim_ts = None;
+        self.timestamp = required_ts;
+        true
+    }
+
+    // Drops any frames past the end of the track declared by the container. Returns false if
+    // the entire decoded buffer is past the end.
+    fn trim_to_track_end(&mut self, packet_ts: u64) -> bool {
+        if !self.settings.enable_gapless {
+            return true;
+        }
+        let Some(num_frames) = self.num_frames else {
+            return true;
+        };
+        let start_frame = self.timestamp_to_frames(packet_ts);
+        let remaining_frames = self
+            .timestamp_to_frames(num_frames)
+            .saturating_sub(start_frame);
+        let frames = self.buf_len / self.output_channels;
+        if remaining_frames < frames {
+            self.buf_len = remaining_frames * self.output_channels;
+        }
+        self.buf_len > 0
+    }
+
+    // Holds back silent frames at the end of the buffer until we know whether more audio follows
+    // them. Returns false if the entire buffer was held back.
+    fn defer_trailing_silence(&mut self) -> bool {
+        if !self.settings.trim_trailing_silence {
+            return true;
+        }
+        let channels = self.output_channels;
+        let frames = self.buf_len / channels;
+        let silent_frames = self.buf[..self.buf_len]
+            .chunks_exact(channels)
+            .rev()
+            .take_while(|frame| frame.iter().all(|s| is_silent(*s)))
+            .count();
+        if silent_frames == frames {
+            self.pending_silence_frames += frames;
+            let max_frames =
+                (MAX_TRAILING_SILENCE.as_secs_f64() * self.sample_rate as f64) as usize;
+            if self.pending_silence_frames <= max_frames {
+                return false;
             }
-            if let Some(mut index) = self.buf.iter().position(|s| *s != T::MID) {
-                // Edge case: if the first non-silent sample is on an odd-numbered index, we'll
-                // start on the wrong channel This only matters for stereo outputs
-                if self.output_channels == 2 && index % 2 == 1 {
-                    index -= 1;
-                }
-                self.buf_len -= index;
-                samples_skipped += index;
-                // Trim all the silent samples
-                let buf_no_silence: Vec<T> = self.buf[index..]
-                    .iter()
-                    .map(|b| (*b).mul_amp(self.volume))
-                    .collect();
-
-                // Put the segment without silence at the beginning
-                self.buf[..self.buf_len].copy_from_slice(&buf_no_silence);
-                info!("Skipped {samples_skipped} silent samples");
-                break;
-            } else {
-                samples_skipped += self.buf.len();
+            // Release the oldest silence since it's too far from the end to be trimmed
+            let released_frames = self.pending_silence_frames - max_frames;
+            self.pending_silence_frames = max_frames;
+            self.buf_len = released_frames * channels;
+            self.buf[..self.buf_len].fill(T::MID);
+            return true;
+        }
+
+        // The silence from previous packets wasn't at the end of the track, so put it back
+        let pending_samples = self.pending_silence_frames * channels;
+        let audible_samples = (frames - silent_frames) * channels;
+        let samples_len = pending_samples + audible_samples;
+        if pending_samples > 0 {
+            if samples_len > self.buf.len() {
+                self.buf.resize(samples_len, T::MID);
             }
+            self.buf.copy_within(..audible_samples, pending_samples);
+            self.buf[..pending_samples].fill(T::MID);
         }
+        self.buf_len = samples_len;
+        self.pending_silence_frames = silent_frames;
+        true
+    }
+
+    fn timestamp_to_frames(&self, timestamp: u64) -> usize {
+        let time = self.time_base.calc_time(timestamp);
+        ((time.seconds as f64 + time.frac) * self.sample_rate as f64).round() as usize
+    }
+
+    fn update_metadata(&mut self) {
+        self.metadata.apply_metadata(&mut self.reader.metadata());
+        self.update_replay_gain();
+    }
+
+    fn update_replay_gain(&mut self) {
+        let replay_gain = self
+            .replay_gain_override
+            .unwrap_or_else(|| self.metadata.replay_gain());
+        let factor = replay_gain.factor(&self.settings.replay_gain);
+        self.replay_gain = factor.to_sample();
+    }
+
+    fn create_audio_decoder(
+        track: &Track,
+    ) -> Result<(Box<dyn AudioDecoder>, TimeBase), DecoderError> {
+        let decode_opts = AudioDecoderOptions { verify: true };
+        let Some(CodecParameters::Audio(codec_params)) = &track.codec_params else {
+            return Err(DecoderError::InvalidTrackType);
+        };
+        // If no time base found, fall back to the sample rate. If that's missing too, default to
+        // a dummy one and calculate it from the decoded sample rate later.
+        let time_base = track
+            .time_base
+            .or_else(|| codec_params.sample_rate.map(|rate| TimeBase::new(1, rate)))
+            .unwrap_or_else(|| TimeBase::new(1, 1));
+        match symphonia::default::get_codecs().make_audio_decoder(codec_params, &decode_opts) {
+            Ok(decoder) => Ok((decoder, time_base)),
+            Err(e) => Err(DecoderError::UnsupportedCodec(e)),
+        }
+    }
+
+    fn initialize(&mut self) -> Result<(), DecoderError> {
+        // Encoder delay and padding are removed by the format reader and decoder when gapless
+        // playback is enabled, so there's no need to look for silence here
+        self.next()?;
         Ok(())
     }
 
+    fn set_format(&mut self, sample_rate: usize, channels: &Channels) {
+        self.sample_rate = sample_rate;
+        self.input_channels = channels.count();
+
+        info!("Input channels = {}", self.input_channels);
+        info!("Input sample rate = {sample_rate}");
+
+        self.input_layout = ChannelLayout::from_channels(channels);
+        self.volume
+            .set_ramp_time(self.settings.volume_ramp, sample_rate);
+        self.pause_fade
+            .set_ramp_time(self.settings.pause_fade, sample_rate);
+        self.channel_mixer = ChannelMixer::new(&self.channel_matrix());
+        if self.time_base.denom == 1 {
+            self.time_base = TimeBase::new(1, sample_rate as u32);
+        }
+    }
+
+    fn channel_matrix(&self) -> ChannelMatrix {
+        let input = &self.input_layout;
+        let output = ChannelLayout::from_count(self.output_channels);
+        if output.len() > input.len() {
+            self.settings.upmix.matrix(input, &output)
+        } else {
+            self.settings.downmix.matrix(input, &output)
+        }
+    }
+
     fn adjust_buffer_size(&mut self, samples_length: usize) {
         if samples_length > self.buf.len() {
             self.buf.clear();
@@ -299,57 +810,61 @@ where
             }
         };
 
-        if self.sample_rate == 0 {
-            let spec = decoded.spec();
-            let sample_rate = spec.rate() as usize;
-            self.sample_rate = sample_rate;
-            let channels = spec.channels().count();
-            self.input_channels = channels;
-
-            info!("Input channels = {channels}");
-            info!("Input sample rate = {sample_rate}");
-
-            if channels > 2 {
-                return Err(DecoderError::UnsupportedFormat(
-                    "Audio sources with more than 2 channels are not supported".to_owned(),
-                ));
-            }
-        }
+        let spec = decoded.spec();
+        let format = (spec.rate() as usize != self.sample_rate
+            || spec.channels().count() != self.input_channels)
+            .then(|| (spec.rate() as usize, spec.channels().clone()));
 
         let samples_len = decoded.samples_interleaved();
         self.sample_buf.resize(samples_len, T::MID);
         decoded.copy_to_slice_interleaved(&mut self.sample_buf);
 
-        match (self.input_channels, self.output_channels) {
-            (1, 2) => {
-                self.adjust_buffer_size(samples_len * 2);
+        if let Some((sample_rate, channels)) = format {
+            self.set_format(sample_rate, &channels);
+        }
 
-                let mut i = 0;
-                for sample in self.sample_buf.iter() {
-                    self.buf[i] = (*sample).mul_amp(self.volume);
-                    self.buf[i + 1] = (*sample).mul_amp(self.volume);
-                    i += 2;
-                }
-            }
-            (2, 1) => {
-                self.adjust_buffer_size(samples_len / 2);
+        self.adjust_buffer_size(self.channel_mixer.output_len(samples_len));
+        self.channel_mixer.mix(
+            &self.sample_buf,
+            &mut self.buf[..self.buf_len],
+            self.replay_gain,
+        );
 
-                for (i, sample) in self.sample_buf.chunks_exact(2).enumerate() {
-                    self.buf[i] = (sample[0] + sample[1])
-                        .mul_amp(0.5.to_sample())
-                        .mul_amp(self.volume);
-                }
-            }
-            _ => {
-                self.adjust_buffer_size(samples_len);
+        Ok(())
+    }
 
-                for (i, sample) in self.sample_buf.iter().enumerate() {
-                    self.buf[i] = (*sample).mul_amp(self.volume);
+    fn next_track_packet(&mut self) -> Result<Option<Packet>, DecoderError> {
+        loop {
+            match self.reader.next_packet() {
+                Ok(Some(packet)) => {
+                    // Streams may update their metadata mid-playback
+                    if !self.reader.metadata().is_latest() {
+                        self.update_metadata();
+                    }
+                    if packet.track_id() == self.track_id {
+                        return Ok(Some(packet));
+                    }
+                }
+                Ok(None) => {
+                    if self.pending_silence_frames > 0 {
+                        info!(
+                            "Trimmed {} silent frames from the end of the track",
+                            self.pending_silence_frames
+                        );
+                        self.pending_silence_frames = 0;
+                    }
+                    return Ok(None);
+                }
+                Err(Error::ResetRequired) => {
+                    warn!("Decoder reset required");
+                    return Err(DecoderError::ResetRequired);
+                }
+                Err(e) => {
+                    error!("Error reading next packet: {e:?}");
+                    return Err(DecoderError::DecodeError(e));
                 }
             }
         }
-
-        Ok(())
     }
 
     pub(crate) fn current(&self) -> &[T] {
@@ -357,40 +872,47 @@ where
     }
 
     pub(crate) fn next(&mut self) -> Result<Option<&[T]>, DecoderError> {
-        if self.is_paused {
+        if self.is_paused && (!self.pause_fade.is_ramping() || !self.paused_samples.is_empty()) {
             self.buf.fill(T::MID);
+        } else if !self.paused_samples.is_empty() {
+            // Continue with the rest of the packet that was cut off by pausing
+            self.adjust_buffer_size(self.paused_samples.len());
+            self.buf[..self.buf_len].copy_from_slice(&self.paused_samples);
+            self.paused_samples.clear();
+            let buf = &mut self.buf[..self.buf_len];
+            self.volume.apply(buf, self.output_channels);
+            self.pause_fade.apply(buf, self.output_channels);
         } else {
             loop {
-                let packet = loop {
-                    match self.reader.next_packet() {
-                        Ok(Some(packet)) => {
-                            if packet.track_id() == self.track_id {
-                                if let Some(required_ts) = self.seek_required_ts {
-                                    if packet.ts() < required_ts {
-                                        continue;
-                                    } else {
-                                        self.seek_required_ts = None;
-                                    }
-                                }
-                                break packet;
-                            }
-                        }
-                        Ok(None) => {
-                            return Ok(None);
-                        }
-                        Err(Error::ResetRequired) => {
-                            warn!("Decoder reset required");
-                            return Err(DecoderError::ResetRequired);
-                        }
-                        Err(e) => {
-                            error!("Error reading next packet: {e:?}");
-                            return Err(DecoderError::DecodeError(e));
-                        }
-                    };
+                let packet = match self.seek_packet.take() {
+                    Some(packet) => packet,
+                    None => match self.next_track_packet()? {
+                        Some(packet) => packet,
+                        None => return Ok(None),
+                    },
                 };
                 self.timestamp = packet.ts();
+                self.decoded_bytes += packet.buf().len() as u64;
+                self.decoded_ts += packet.dur();
                 match self.process_output(&packet) {
-                    Ok(()) => break,
+                    Ok(()) => {
+                        if self.trim_to_track_end(packet.ts())
+                            && self.trim_to_seek_target(packet.ts())
+                            && self.defer_trailing_silence()
+                        {
+                            let buf = &mut self.buf[..self.buf_len];
+                            if self.is_paused {
+                                // Keep whatever comes after the end of the fade so it isn't lost
+                                let fade_len = (self.pause_fade.remaining_frames()
+                                    * self.output_channels)
+                                    .min(buf.len());
+                                self.paused_samples.extend_from_slice(&buf[fade_len..]);
+                            }
+                            self.volume.apply(buf, self.output_channels);
+                            self.pause_fade.apply(buf, self.output_channels);
+                            break;
+                        }
+                    }
                     Err(DecoderError::Recoverable(_)) => {
                         // Just read the next packet on a recoverable error
                     }
@@ -404,3 +926,33 @@ where
         Ok(Some(self.current()))
     }
 }
+
+// The sample rate and channels declared by the track, if it declares both
+fn track_format(track: &Track) -> Option<(usize, &Channels)> {
+    let Some(CodecParameters::Audio(codec_params)) = &track.codec_params else {
+        return None;
+    };
+    Some((
+        codec_params.sample_rate? as usize,
+        codec_params.channels.as_ref()?,
+    ))
+}
+
+// The bitrate in bits per second, which is only declared by constant bitrate codecs such as PCM
+fn track_bitrate(track: &Track) -> Option<u64> {
+    let Some(CodecParameters::Audio(codec_params)) = &track.codec_params else {
+        return None;
+    };
+    let bits_per_frame =
+        codec_params.bits_per_coded_sample? as u64 * codec_params.channels.as_ref()?.count() as u64;
+    Some(bits_per_frame * codec_params.sample_rate? as u64)
+}
+
+fn is_silent<T: DaspSample>(sample: T) -> bool {
+    let sample: f32 = sample.to_float_sample().to_sample();
+    sample.abs() <= SILENCE_THRESHOLD
+}
+
+#[cfg(test)]
+#[path = "./decoder_test.rs"]
+mod decoder_test;
@@ -0,0 +1,110 @@
+use super::{TagKey, TrackMetadata, db_to_linear};
+
+// ReplayGain 2.0 targets -18 LUFS while R128 gain tags are relative to -23 LUFS
+const R128_OFFSET_DB: f32 = 5.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    /// Uses the album gain, falling back to the track gain if the album gain is missing.
+    Album,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayGainSettings {
+    pub mode: ReplayGainMode,
+    /// Extra gain in dB applied on top of the ReplayGain adjustment.
+    pub preamp_db: f32,
+    /// Reduces the gain if the track's peak would otherwise exceed full scale.
+    pub prevent_clipping: bool,
+}
+
+impl Default for ReplayGainSettings {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::Off,
+            preamp_db: 0.0,
+            prevent_clipping: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReplayGain {
+    pub track_gain_db: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain_db: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    pub fn from_metadata(metadata: &TrackMetadata) -> Self {
+        let gain = |key, r128_key| {
+            parse_gain(metadata.get(key)).or_else(|| parse_r128_gain(metadata.get(r128_key)))
+        };
+        Self {
+            track_gain_db: gain(TagKey::ReplayGainTrackGain, TagKey::R128TrackGain),
+            track_peak: parse_peak(metadata.get(TagKey::ReplayGainTrackPeak)),
+            album_gain_db: gain(TagKey::ReplayGainAlbumGain, TagKey::R128AlbumGain),
+            album_peak: parse_peak(metadata.get(TagKey::ReplayGainAlbumPeak)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.track_gain_db.is_none() && self.album_gain_db.is_none()
+    }
+
+    /// The linear gain factor to apply for the given settings. Returns 1 if ReplayGain is
+    /// disabled or the track isn't tagged.
+    pub fn factor(&self, settings: &ReplayGainSettings) -> f32 {
+        let (gain_db, peak) = match settings.mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => (self.track_gain_db, self.track_peak),
+            ReplayGainMode::Album => match self.album_gain_db {
+                Some(gain_db) => (Some(gain_db), self.album_peak),
+                None => (self.track_gain_db, self.track_peak),
+            },
+        };
+        let Some(gain_db) = gain_db else {
+            return 1.0;
+        };
+
+        let factor = db_to_linear(gain_db + settings.preamp_db);
+        match peak {
+            Some(peak) if settings.prevent_clipping && peak > 0.0 => factor.min(1.0 / peak),
+            _ => factor,
+        }
+    }
+}
+
+impl TrackMetadata {
+    pub fn replay_gain(&self) -> ReplayGain {
+        ReplayGain::from_metadata(self)
+    }
+}
+
+// Gains are usually written as "-6.25 dB"
+fn parse_gain(value: Option<&str>) -> Option<f32> {
+    let value = value?.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    value.trim().parse().ok().filter(|v: &f32| v.is_finite())
+}
+
+fn parse_peak(value: Option<&str>) -> Option<f32> {
+    value?.trim().parse().ok().filter(|v: &f32| v.is_finite())
+}
+
+// R128 gains are stored as Q7.8 fixed point integers
+fn parse_r128_gain(value: Option<&str>) -> Option<f32> {
+    let gain: i16 = value?.trim().parse().ok()?;
+    Some(gain as f32 / 256.0 + R128_OFFSET_DB)
+}
+
+#[cfg(test)]
+#[path = "./replay_gain_test.rs"]
+mod replay_gain_test;
@@ -0,0 +1,71 @@
+use super::{ReplayGain, ReplayGainMode, ReplayGainSettings};
+use crate::decoder::{Tag, TrackMetadata};
+
+fn settings(mode: ReplayGainMode) -> ReplayGainSettings {
+    ReplayGainSettings {
+        mode,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn parse_replay_gain_tags() {
+    let mut metadata = TrackMetadata::default();
+    metadata.apply_tags(vec![
+        Tag::new("REPLAYGAIN_TRACK_GAIN", "-6.02 dB"),
+        Tag::new("REPLAYGAIN_TRACK_PEAK", "0.988"),
+        Tag::new("TXXX:REPLAYGAIN_ALBUM_GAIN", "+1.5 dB"),
+    ]);
+    let replay_gain = metadata.replay_gain();
+
+    assert_eq!(Some(-6.02), replay_gain.track_gain_db);
+    assert_eq!(Some(0.988), replay_gain.track_peak);
+    assert_eq!(Some(1.5), replay_gain.album_gain_db);
+    assert_eq!(None, replay_gain.album_peak);
+}
+
+#[test]
+fn parse_r128_tags() {
+    let mut metadata = TrackMetadata::default();
+    metadata.apply_tags(vec![Tag::new("R128_TRACK_GAIN", "-1792")]);
+
+    // -7 dB relative to -23 LUFS is -2 dB relative to the ReplayGain reference
+    assert_eq!(Some(-2.0), metadata.replay_gain().track_gain_db);
+}
+
+#[test]
+fn track_and_album_modes() {
+    let replay_gain = ReplayGain {
+        track_gain_db: Some(-20.0),
+        album_gain_db: Some(-40.0),
+        ..Default::default()
+    };
+
+    assert_eq!(1.0, replay_gain.factor(&settings(ReplayGainMode::Off)));
+    assert!((replay_gain.factor(&settings(ReplayGainMode::Track)) - 0.1).abs() < 1e-6);
+    assert!((replay_gain.factor(&settings(ReplayGainMode::Album)) - 0.01).abs() < 1e-6);
+}
+
+#[test]
+fn album_falls_back_to_track() {
+    let replay_gain = ReplayGain {
+        track_gain_db: Some(-20.0),
+        ..Default::default()
+    };
+
+    assert!((replay_gain.factor(&settings(ReplayGainMode::Album)) - 0.1).abs() < 1e-6);
+}
+
+#[test]
+fn prevent_clipping() {
+    let replay_gain = ReplayGain {
+        track_gain_db: Some(6.0),
+        track_peak: Some(0.8),
+        ..Default::default()
+    };
+    let mut settings = settings(ReplayGainMode::Track);
+
+    assert_eq!(1.25, replay_gain.factor(&settings));
+    settings.prevent_clipping = false;
+    assert!(replay_gain.factor(&settings) > 1.9);
+}
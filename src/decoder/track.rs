@@ -0,0 +1,27 @@
+use symphonia::core::codecs::CodecParameters;
+pub use symphonia::core::codecs::audio::AudioCodecId;
+use symphonia::core::formats::Track;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackInfo {
+    pub id: u32,
+    pub codec: AudioCodecId,
+    pub language: Option<String>,
+    pub channels: Option<usize>,
+    pub sample_rate: Option<u32>,
+}
+
+impl TrackInfo {
+    pub(crate) fn from_track(track: &Track) -> Option<Self> {
+        let Some(CodecParameters::Audio(codec_params)) = &track.codec_params else {
+            return None;
+        };
+        Some(Self {
+            id: track.id,
+            codec: codec_params.codec,
+            language: track.language.clone(),
+            channels: codec_params.channels.as_ref().map(|c| c.count()),
+            sample_rate: codec_params.sample_rate,
+        })
+    }
+}
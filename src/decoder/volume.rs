@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use dasp::sample::Sample as DaspSample;
+
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+pub fn linear_to_db(gain: f32) -> f32 {
+    20.0 * gain.log10()
+}
+
+/// Maps a 0 to 1 volume scale, such as the position of a volume slider, to a linear gain.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum VolumeCurve {
+    Linear,
+    /// Approximates perceived loudness without needing to choose a range.
+    #[default]
+    Cubic,
+    /// Spreads the scale evenly over the given number of decibels below full volume. 0 is
+    /// always silent.
+    Decibel {
+        range_db: f32,
+    },
+}
+
+impl VolumeCurve {
+    pub fn to_linear(&self, level: f32) -> f32 {
+        let level = level.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => level,
+            Self::Cubic => level.powi(3),
+            Self::Decibel { .. } if level == 0.0 => 0.0,
+            Self::Decibel { range_db } => db_to_linear((level - 1.0) * range_db),
+        }
+    }
+
+    pub fn from_linear(&self, gain: f32) -> f32 {
+        let gain = gain.max(0.0);
+        match self {
+            Self::Linear => gain,
+            Self::Cubic => gain.cbrt(),
+            Self::Decibel { .. } if gain == 0.0 => 0.0,
+            Self::Decibel { range_db } => (1.0 + linear_to_db(gain) / range_db).max(0.0),
+        }
+    }
+}
+
+/// Interpolates between volume levels one frame at a time so changes don't produce audible
+/// steps.
+#[derive(Clone, Debug)]
+pub(crate) struct VolumeRamp {
+    current: f32,
+    target: f32,
+    is_muted: bool,
+    step: f32,
+    ramp_frames: usize,
+    remaining_frames: usize,
+}
+
+impl VolumeRamp {
+    pub(crate) fn new(volume: f32) -> Self {
+        Self {
+            current: volume,
+            target: volume,
+            is_muted: false,
+            step: 0.0,
+            ramp_frames: 0,
+            remaining_frames: 0,
+        }
+    }
+
+    pub(crate) fn set_ramp_time(&mut self, ramp_time: Duration, sample_rate: usize) {
+        self.ramp_frames = (ramp_time.as_secs_f64() * sample_rate as f64).round() as usize;
+    }
+
+    pub(crate) fn set_target(&mut self, volume: f32) {
+        self.target = volume;
+        self.start_ramp();
+    }
+
+    /// The volume that will be used when unmuted.
+    pub(crate) fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub(crate) fn set_muted(&mut self, is_muted: bool) {
+        self.is_muted = is_muted;
+        self.start_ramp();
+    }
+
+    pub(crate) fn is_muted(&self) -> bool {
+        self.is_muted
+    }
+
+    fn effective_target(&self) -> f32 {
+        if self.is_muted { 0.0 } else { self.target }
+    }
+
+    fn start_ramp(&mut self) {
+        let target = self.effective_target();
+        if self.ramp_frames == 0 {
+            self.current = target;
+            self.remaining_frames = 0;
+        } else {
+            self.remaining_frames = self.ramp_frames;
+            self.step = (target - self.current) / self.ramp_frames as f32;
+        }
+    }
+
+    pub(crate) fn is_ramping(&self) -> bool {
+        self.remaining_frames > 0
+    }
+
+    /// The number of frames until the target volume is reached.
+    pub(crate) fn remaining_frames(&self) -> usize {
+        self.remaining_frames
+    }
+
+    pub(crate) fn apply<T: DaspSample>(&mut self, samples: &mut [T], channels: usize) {
+        if !self.is_ramping() {
+            if self.current != 1.0 {
+                let gain: T::Float = self.current.to_sample();
+                for sample in samples {
+                    *sample = sample.mul_amp(gain);
+                }
+            }
+            return;
+        }
+
+        for frame in samples.chunks_exact_mut(channels) {
+            if self.remaining_frames > 0 {
+                self.remaining_frames -= 1;
+                self.current = if self.remaining_frames == 0 {
+                    self.effective_target()
+                } else {
+                    self.current + self.step
+                };
+            }
+            let gain: T::Float = self.current.to_sample();
+            for sample in frame {
+                *sample = sample.mul_amp(gain);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./volume_test.rs"]
+mod volume_test;
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use super::{VolumeCurve, VolumeRamp, db_to_linear, linear_to_db};
+
+#[test]
+fn ramp_is_interpolated_per_frame() {
+    let mut ramp = VolumeRamp::new(1.0);
+    ramp.set_ramp_time(Duration::from_millis(4), 1000);
+    ramp.set_target(0.0);
+    let mut samples = [1.0f32; 8];
+    ramp.apply(&mut samples, 2);
+
+    assert_eq!([0.75, 0.75, 0.5, 0.5, 0.25, 0.25, 0.0, 0.0], samples);
+    assert!(!ramp.is_ramping());
+}
+
+#[test]
+fn ramp_continues_across_buffers() {
+    let mut ramp = VolumeRamp::new(0.0);
+    ramp.set_ramp_time(Duration::from_millis(4), 1000);
+    ramp.set_target(1.0);
+    let mut samples = [1.0f32; 2];
+    ramp.apply(&mut samples, 1);
+    assert_eq!([0.25, 0.5], samples);
+
+    let mut samples = [1.0f32; 3];
+    ramp.apply(&mut samples, 1);
+    assert_eq!([0.75, 1.0, 1.0], samples);
+}
+
+#[test]
+fn retarget_mid_ramp() {
+    let mut ramp = VolumeRamp::new(1.0);
+    ramp.set_ramp_time(Duration::from_millis(2), 1000);
+    ramp.set_target(0.0);
+    ramp.apply(&mut [1.0f32], 1);
+    ramp.set_target(1.0);
+    let mut samples = [1.0f32; 2];
+    ramp.apply(&mut samples, 1);
+
+    // The new ramp starts from the current level rather than jumping back
+    assert_eq!([0.75, 1.0], samples);
+}
+
+#[test]
+fn no_ramp_time() {
+    let mut ramp = VolumeRamp::new(1.0);
+    ramp.set_target(0.5);
+    let mut samples = [1.0f32; 2];
+    ramp.apply(&mut samples, 1);
+
+    assert_eq!([0.5, 0.5], samples);
+}
+
+#[test]
+fn mute_restores_volume() {
+    let mut ramp = VolumeRamp::new(0.5);
+    ramp.set_muted(true);
+    let mut samples = [1.0f32; 2];
+    ramp.apply(&mut samples, 1);
+    assert_eq!([0.0, 0.0], samples);
+    assert_eq!(0.5, ramp.target());
+
+    ramp.set_muted(false);
+    let mut samples = [1.0f32; 2];
+    ramp.apply(&mut samples, 1);
+    assert_eq!([0.5, 0.5], samples);
+}
+
+#[test]
+fn volume_curves() {
+    let decibel = VolumeCurve::Decibel { range_db: 60.0 };
+
+    assert_eq!(0.5, VolumeCurve::Linear.to_linear(0.5));
+    assert_eq!(0.125, VolumeCurve::Cubic.to_linear(0.5));
+    assert_eq!(0.0, decibel.to_linear(0.0));
+    assert_eq!(1.0, decibel.to_linear(1.0));
+    assert!((linear_to_db(decibel.to_linear(0.5)) + 30.0).abs() < 1e-4);
+    assert!((decibel.from_linear(decibel.to_linear(0.25)) - 0.25).abs() < 1e-4);
+    assert!((VolumeCurve::Cubic.from_linear(0.125) - 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn decibel_conversion() {
+    assert!((db_to_linear(-6.0) - 0.501_187).abs() < 1e-6);
+    assert!((linear_to_db(0.1) + 20.0).abs() < 1e-4);
+    assert_eq!(f32::NEG_INFINITY, linear_to_db(0.0));
+}
@@ -0,0 +1,124 @@
+use std::f64::consts::PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterType {
+    Peaking,
+    LowShelf,
+    HighShelf,
+    LowPass,
+    HighPass,
+}
+
+// Normalized biquad coefficients from the Audio EQ Cookbook
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct BiquadCoefficients {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl BiquadCoefficients {
+    pub(crate) fn new(
+        filter_type: FilterType,
+        sample_rate: usize,
+        frequency: f32,
+        gain_db: f32,
+        q: f32,
+    ) -> Self {
+        // Frequencies above Nyquist would make the filter unstable
+        let frequency = (frequency as f64).clamp(1.0, sample_rate as f64 * 0.49);
+        let w0 = 2.0 * PI * frequency / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q.max(0.01) as f64);
+        let a = 10f64.powf(gain_db as f64 / 40.0);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match filter_type {
+            FilterType::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            FilterType::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha,
+            ),
+            FilterType::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha,
+            ),
+            FilterType::LowPass => (
+                (1.0 - cos) / 2.0,
+                1.0 - cos,
+                (1.0 - cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            FilterType::HighPass => (
+                (1.0 + cos) / 2.0,
+                -(1.0 + cos),
+                (1.0 + cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+        };
+
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    // Coefficients that have already been divided by a0
+    pub(crate) fn from_normalized(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b0: b[0],
+            b1: b[1],
+            b2: b[2],
+            a1: a[0],
+            a2: a[1],
+        }
+    }
+}
+
+// Passes audio through unchanged, which is used until the sample rate is known
+impl Default for BiquadCoefficients {
+    fn default() -> Self {
+        Self::from_normalized([1.0, 0.0, 0.0], [0.0, 0.0])
+    }
+}
+
+// Transposed direct form II, which handles coefficient changes between samples gracefully
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct BiquadState {
+    s1: f64,
+    s2: f64,
+}
+
+impl BiquadState {
+    pub(crate) fn process(&mut self, coefficients: &BiquadCoefficients, sample: f32) -> f32 {
+        let input = sample as f64;
+        let output = coefficients.b0 * input + self.s1;
+        self.s1 = coefficients.b1 * input - coefficients.a1 * output + self.s2;
+        self.s2 = coefficients.b2 * input - coefficients.a2 * output;
+        output as f32
+    }
+}
@@ -0,0 +1,140 @@
+use super::Effect;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EffectId(u64);
+
+struct ChainEntry {
+    id: EffectId,
+    effect: Box<dyn Effect>,
+    is_enabled: bool,
+}
+
+/// An ordered list of effects. Each effect processes the output of the previous one.
+#[derive(Default)]
+pub struct EffectChain {
+    entries: Vec<ChainEntry>,
+    next_id: u64,
+    sample_rate: usize,
+    channels: usize,
+}
+
+impl EffectChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, effect: impl Effect + 'static) -> EffectId {
+        self.insert(self.entries.len(), effect)
+    }
+
+    /// Inserts the effect at the given position, shifting the effects after it. The position is
+    /// clamped to the length of the chain.
+    pub fn insert(&mut self, index: usize, mut effect: impl Effect + 'static) -> EffectId {
+        let id = EffectId(self.next_id);
+        self.next_id += 1;
+        if self.sample_rate > 0 {
+            effect.initialize(self.sample_rate, self.channels);
+        }
+        self.entries.insert(index.min(self.entries.len()), ChainEntry {
+            id,
+            effect: Box::new(effect),
+            is_enabled: true,
+        });
+        id
+    }
+
+    pub fn remove(&mut self, id: EffectId) -> Option<Box<dyn Effect>> {
+        let index = self.index_of(id)?;
+        Some(self.entries.remove(index).effect)
+    }
+
+    /// Moves the effect to a new position in the chain. Returns false if the effect wasn't found.
+    pub fn move_to(&mut self, id: EffectId, index: usize) -> bool {
+        let Some(current) = self.index_of(id) else {
+            return false;
+        };
+        let entry = self.entries.remove(current);
+        self.entries.insert(index.min(self.entries.len()), entry);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The ids of the effects in processing order.
+    pub fn ids(&self) -> impl Iterator<Item = EffectId> + '_ {
+        self.entries.iter().map(|e| e.id)
+    }
+
+    pub fn get_mut(&mut self, id: EffectId) -> Option<&mut dyn Effect> {
+        let index = self.index_of(id)?;
+        Some(self.entries[index].effect.as_mut())
+    }
+
+    /// Disabled effects are skipped without being removed from the chain.
+    pub fn set_enabled(&mut self, id: EffectId, is_enabled: bool) {
+        if let Some(index) = self.index_of(id) {
+            let entry = &mut self.entries[index];
+            if is_enabled && !entry.is_enabled {
+                // The effect's state is stale since it hasn't seen the audio while disabled
+                entry.effect.reset();
+            }
+            entry.is_enabled = is_enabled;
+        }
+    }
+
+    pub fn is_enabled(&self, id: EffectId) -> bool {
+        self.index_of(id)
+            .map(|index| self.entries[index].is_enabled)
+            .unwrap_or(false)
+    }
+
+    fn index_of(&self, id: EffectId) -> Option<usize> {
+        self.entries.iter().position(|e| e.id == id)
+    }
+}
+
+impl Effect for EffectChain {
+    fn initialize(&mut self, sample_rate: usize, channels: usize) {
+        self.sample_rate = sample_rate;
+        self.channels = channels;
+        for entry in &mut self.entries {
+            entry.effect.initialize(sample_rate, channels);
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for entry in self.entries.iter_mut().filter(|e| e.is_enabled) {
+            entry.effect.process(samples);
+        }
+    }
+
+    fn reset(&mut self) {
+        for entry in &mut self.entries {
+            entry.effect.reset();
+        }
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) {
+        // Audio held back by one effect still has to go through the effects after it
+        let mut tail = Vec::new();
+        for entry in self.entries.iter_mut().filter(|e| e.is_enabled) {
+            entry.effect.process(&mut tail);
+            entry.effect.flush(&mut tail);
+        }
+        output.extend(tail);
+    }
+}
+
+#[cfg(test)]
+#[path = "./chain_test.rs"]
+mod chain_test;
@@ -0,0 +1,103 @@
+use super::EffectChain;
+use crate::effects::Effect;
+
+struct Gain(f32);
+
+impl Effect for Gain {
+    fn initialize(&mut self, _sample_rate: usize, _channels: usize) {}
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            *sample *= self.0;
+        }
+    }
+
+    fn reset(&mut self) {}
+}
+
+struct Offset(f32);
+
+impl Effect for Offset {
+    fn initialize(&mut self, _sample_rate: usize, _channels: usize) {}
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            *sample += self.0;
+        }
+    }
+
+    fn reset(&mut self) {}
+}
+
+// Holds back the last sample until it's flushed
+struct Delay(f32);
+
+impl Effect for Delay {
+    fn initialize(&mut self, _sample_rate: usize, _channels: usize) {}
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            std::mem::swap(sample, &mut self.0);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.0 = 0.0;
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) {
+        output.push(self.0);
+        self.reset();
+    }
+}
+
+#[test]
+fn effects_run_in_order() {
+    let mut chain = EffectChain::new();
+    chain.push(Gain(2.0));
+    chain.push(Offset(1.0));
+    let mut samples = [1.0, 2.0];
+    chain.process(&mut samples);
+
+    assert_eq!([3.0, 5.0], samples);
+}
+
+#[test]
+fn reorder_effects() {
+    let mut chain = EffectChain::new();
+    let gain = chain.push(Gain(2.0));
+    let offset = chain.push(Offset(1.0));
+    assert!(chain.move_to(offset, 0));
+    let mut samples = [1.0];
+    chain.process(&mut samples);
+
+    assert_eq!([4.0], samples);
+    assert_eq!(vec![offset, gain], chain.ids().collect::<Vec<_>>());
+}
+
+#[test]
+fn disable_and_remove() {
+    let mut chain = EffectChain::new();
+    let gain = chain.push(Gain(2.0));
+    let offset = chain.push(Offset(1.0));
+    chain.set_enabled(gain, false);
+    let mut samples = [1.0];
+    chain.process(&mut samples);
+    assert_eq!([2.0], samples);
+
+    assert!(chain.remove(offset).is_some());
+    assert!(chain.remove(offset).is_none());
+    assert_eq!(1, chain.len());
+}
+
+#[test]
+fn flushed_audio_goes_through_later_effects() {
+    let mut chain = EffectChain::new();
+    chain.push(Delay(0.0));
+    chain.push(Gain(2.0));
+    let mut samples = vec![1.0, 2.0];
+    chain.process(&mut samples);
+    chain.flush(&mut samples);
+
+    assert_eq!(vec![0.0, 2.0, 4.0], samples);
+}
@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use super::Effect;
+
+// Changes are faded in over this time so toggling a control doesn't click
+const RAMP_TIME: Duration = Duration::from_millis(20);
+
+/// Balance, channel swapping, polarity inversion and mono fold-down. Apart from mono, these only
+/// affect the front left and right channels.
+#[derive(Clone, Debug)]
+pub struct ChannelControls {
+    balance: f32,
+    is_swapped: bool,
+    invert_left: bool,
+    invert_right: bool,
+    is_mono: bool,
+    // The channels that are averaged for mono
+    main_channels: Vec<bool>,
+    channels: usize,
+    ramp_frames: usize,
+    remaining_frames: usize,
+    // Row-major matrix of how much each input channel contributes to each output channel
+    matrix: Vec<f32>,
+    target: Vec<f32>,
+    step: Vec<f32>,
+    frame_buf: Vec<f32>,
+}
+
+impl Default for ChannelControls {
+    fn default() -> Self {
+        Self {
+            balance: 0.0,
+            is_swapped: false,
+            invert_left: false,
+            invert_right: false,
+            is_mono: false,
+            main_channels: Vec::new(),
+            channels: 0,
+            ramp_frames: 0,
+            remaining_frames: 0,
+            matrix: Vec::new(),
+            target: Vec::new(),
+            step: Vec::new(),
+            frame_buf: Vec::new(),
+        }
+    }
+}
+
+impl ChannelControls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the balance from -1 (left only) to 1 (right only).
+    pub fn set_balance(&mut self, balance: f32) {
+        self.balance = balance.clamp(-1.0, 1.0);
+        self.start_ramp();
+    }
+
+    pub fn balance(&self) -> f32 {
+        self.balance
+    }
+
+    pub fn set_channels_swapped(&mut self, is_swapped: bool) {
+        self.is_swapped = is_swapped;
+        self.start_ramp();
+    }
+
+    pub fn channels_swapped(&self) -> bool {
+        self.is_swapped
+    }
+
+    pub fn set_polarity_inverted(&mut self, invert_left: bool, invert_right: bool) {
+        self.invert_left = invert_left;
+        self.invert_right = invert_right;
+        self.start_ramp();
+    }
+
+    /// Whether the left and right channels are inverted.
+    pub fn polarity_inverted(&self) -> (bool, bool) {
+        (self.invert_left, self.invert_right)
+    }
+
+    /// Plays the average of the main channels on each of them.
+    pub fn set_mono(&mut self, is_mono: bool) {
+        self.is_mono = is_mono;
+        self.start_ramp();
+    }
+
+    pub fn is_mono(&self) -> bool {
+        self.is_mono
+    }
+
+    /// Sets which channels carry the source's audio. Mono only averages these and leaves the
+    /// others unchanged, so the LFE and channels that upmixing left silent don't affect the level.
+    /// By default this is every channel except the LFE.
+    pub fn set_main_channels(&mut self, main_channels: &[bool]) {
+        let main_channels = if main_channels.len() == self.channels {
+            main_channels.to_vec()
+        } else {
+            default_main_channels(self.channels)
+        };
+        if main_channels != self.main_channels {
+            self.main_channels = main_channels;
+            self.start_ramp();
+        }
+    }
+
+    /// Whether any of the controls are changing the audio or are still fading to or from their
+    /// last setting.
+    pub fn is_active(&self) -> bool {
+        self.remaining_frames > 0 || self.matrix != identity(self.channels)
+    }
+
+    fn target_matrix(&self) -> Vec<f32> {
+        let channels = self.channels;
+        let mut matrix = identity(channels);
+        let main_count = self.main_channels.iter().filter(|c| **c).count();
+        if self.is_mono && main_count > 0 {
+            for (row, is_main_row) in matrix.chunks_exact_mut(channels).zip(&self.main_channels) {
+                if !is_main_row {
+                    continue;
+                }
+                for (weight, is_main) in row.iter_mut().zip(&self.main_channels) {
+                    *weight = if *is_main {
+                        1.0 / main_count as f32
+                    } else {
+                        0.0
+                    };
+                }
+            }
+        }
+        if channels < 2 {
+            return matrix;
+        }
+
+        if self.is_swapped {
+            let (left, right) = matrix.split_at_mut(channels);
+            left.swap_with_slice(&mut right[..channels]);
+        }
+        let left_gain = (1.0 - self.balance).min(1.0) * if self.invert_left { -1.0 } else { 1.0 };
+        let right_gain = (1.0 + self.balance).min(1.0) * if self.invert_right { -1.0 } else { 1.0 };
+        for (row, gain) in [left_gain, right_gain].into_iter().enumerate() {
+            for weight in &mut matrix[row * channels..(row + 1) * channels] {
+                *weight *= gain;
+            }
+        }
+        matrix
+    }
+
+    fn start_ramp(&mut self) {
+        self.target = self.target_matrix();
+        if self.ramp_frames == 0 {
+            self.matrix.clone_from(&self.target);
+            self.remaining_frames = 0;
+            return;
+        }
+        self.step = self
+            .target
+            .iter()
+            .zip(&self.matrix)
+            .map(|(target, current)| (target - current) / self.ramp_frames as f32)
+            .collect();
+        self.remaining_frames = self.ramp_frames;
+    }
+}
+
+// Layouts with six or more channels have the LFE fourth
+fn default_main_channels(channels: usize) -> Vec<bool> {
+    (0..channels).map(|i| channels < 6 || i != 3).collect()
+}
+
+fn identity(channels: usize) -> Vec<f32> {
+    (0..channels * channels)
+        .map(|i| if i % (channels + 1) == 0 { 1.0 } else { 0.0 })
+        .collect()
+}
+
+impl Effect for ChannelControls {
+    fn initialize(&mut self, sample_rate: usize, channels: usize) {
+        if channels != self.channels || self.main_channels.len() != channels {
+            self.main_channels = default_main_channels(channels);
+        }
+        self.channels = channels;
+        self.ramp_frames = (RAMP_TIME.as_secs_f64() * sample_rate as f64).round() as usize;
+        self.matrix = self.target_matrix();
+        self.target.clone_from(&self.matrix);
+        self.remaining_frames = 0;
+        self.frame_buf = vec![0.0; channels];
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        if !self.is_active() {
+            return;
+        }
+        let channels = self.channels;
+        for frame in samples.chunks_exact_mut(channels) {
+            if self.remaining_frames > 0 {
+                self.remaining_frames -= 1;
+                if self.remaining_frames == 0 {
+                    self.matrix.clone_from(&self.target);
+                } else {
+                    for (weight, step) in self.matrix.iter_mut().zip(&self.step) {
+                        *weight += step;
+                    }
+                }
+            }
+
+            self.frame_buf.copy_from_slice(frame);
+            for (sample, weights) in frame.iter_mut().zip(self.matrix.chunks_exact(channels)) {
+                *sample = weights
+                    .iter()
+                    .zip(&self.frame_buf)
+                    .map(|(w, s)| w * s)
+                    .sum();
+            }
+        }
+    }
+
+    fn reset(&mut self) {}
+}
+
+#[cfg(test)]
+#[path = "./channel_controls_test.rs"]
+mod channel_controls_test;
@@ -0,0 +1,106 @@
+use super::ChannelControls;
+use crate::effects::Effect;
+
+// Processes enough frames for any ramp to finish and returns the last frame
+fn settled_frame(controls: &mut ChannelControls, frame: &[f32]) -> Vec<f32> {
+    let mut samples: Vec<f32> = frame
+        .iter()
+        .copied()
+        .cycle()
+        .take(2000 * frame.len())
+        .collect();
+    controls.process(&mut samples);
+    samples[samples.len() - frame.len()..].to_vec()
+}
+
+fn stereo_controls() -> ChannelControls {
+    let mut controls = ChannelControls::new();
+    controls.initialize(48000, 2);
+    controls
+}
+
+#[test]
+fn neutral_is_inactive() {
+    let mut controls = stereo_controls();
+    assert!(!controls.is_active());
+    assert_eq!(
+        vec![0.5, -0.25],
+        settled_frame(&mut controls, &[0.5, -0.25])
+    );
+}
+
+#[test]
+fn swap() {
+    let mut controls = stereo_controls();
+    controls.set_channels_swapped(true);
+    assert_eq!(
+        vec![-0.25, 0.5],
+        settled_frame(&mut controls, &[0.5, -0.25])
+    );
+}
+
+#[test]
+fn mono() {
+    let mut controls = stereo_controls();
+    controls.set_mono(true);
+    assert_eq!(vec![0.5, 0.5], settled_frame(&mut controls, &[1.0, 0.0]));
+
+    controls.set_mono(false);
+    assert_eq!(vec![1.0, 0.0], settled_frame(&mut controls, &[1.0, 0.0]));
+    assert!(!controls.is_active());
+}
+
+#[test]
+fn mono_multichannel() {
+    let mut controls = ChannelControls::new();
+    controls.initialize(48000, 4);
+    controls.set_mono(true);
+    assert_eq!(
+        vec![0.25; 4],
+        settled_frame(&mut controls, &[1.0, 0.0, 0.0, 0.0])
+    );
+}
+
+#[test]
+fn mono_surround_skips_lfe() {
+    let mut controls = ChannelControls::new();
+    controls.initialize(48000, 6);
+    controls.set_mono(true);
+    assert_eq!(
+        vec![0.2, 0.2, 0.2, 0.5, 0.2, 0.2],
+        settled_frame(&mut controls, &[1.0, 0.0, 0.0, 0.5, 0.0, 0.0])
+    );
+}
+
+#[test]
+fn mono_surround_with_stereo_source() {
+    let mut controls = ChannelControls::new();
+    controls.initialize(48000, 6);
+    // Only the front channels were upmixed from the source
+    controls.set_main_channels(&[true, true, false, false, false, false]);
+    controls.set_mono(true);
+    assert_eq!(
+        vec![0.5, 0.5, 0.0, 0.25, 0.0, 0.0],
+        settled_frame(&mut controls, &[1.0, 0.0, 0.0, 0.25, 0.0, 0.0])
+    );
+}
+
+#[test]
+fn balance_and_polarity() {
+    let mut controls = stereo_controls();
+    controls.set_balance(0.5);
+    controls.set_polarity_inverted(false, true);
+    assert_eq!(vec![0.5, -1.0], settled_frame(&mut controls, &[1.0, 1.0]));
+}
+
+#[test]
+fn changes_are_gradual() {
+    let mut controls = stereo_controls();
+    controls.set_balance(1.0);
+    let mut samples = vec![1.0; 20];
+    controls.process(&mut samples);
+
+    assert!(samples[18] < 1.0);
+    assert!(samples[18] > 0.9);
+    assert!(samples[18] < samples[16]);
+}
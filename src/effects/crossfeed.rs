@@ -0,0 +1,107 @@
+use std::f32::consts::PI;
+
+use super::Effect;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrossfeedSettings {
+    /// Frequencies below this are fed to the opposite channel.
+    pub cutoff: f32,
+    /// Difference in level between the direct and crossfed signal at low frequencies. Lower
+    /// values give a stronger effect.
+    pub feed_db: f32,
+}
+
+impl Default for CrossfeedSettings {
+    fn default() -> Self {
+        Self {
+            cutoff: 700.0,
+            feed_db: 4.5,
+        }
+    }
+}
+
+/// Mixes low frequencies from each stereo channel into the other, similar to how speakers are
+/// heard, to make hard-panned recordings more comfortable on headphones. Centered audio is
+/// unaffected and the filter's delay approximates the time it takes sound to reach the far ear.
+///
+/// Only stereo output is processed.
+#[derive(Clone, Debug)]
+pub struct Crossfeed {
+    settings: CrossfeedSettings,
+    sample_rate: usize,
+    is_stereo: bool,
+    feed: f32,
+    lowpass_coefficient: f32,
+    lowpass: [f32; 2],
+}
+
+impl Crossfeed {
+    pub fn new(settings: CrossfeedSettings) -> Self {
+        Self {
+            settings,
+            sample_rate: 0,
+            is_stereo: false,
+            feed: 0.0,
+            lowpass_coefficient: 0.0,
+            lowpass: [0.0; 2],
+        }
+    }
+
+    pub fn settings(&self) -> &CrossfeedSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: CrossfeedSettings) {
+        self.settings = settings;
+        if self.sample_rate > 0 {
+            self.update_coefficients();
+        }
+    }
+
+    fn update_coefficients(&mut self) {
+        // A hard-panned signal ends up split between the channels with the given difference
+        self.feed = 1.0 / (1.0 + 10f32.powf(self.settings.feed_db / 20.0));
+        let cutoff = self
+            .settings
+            .cutoff
+            .clamp(1.0, self.sample_rate as f32 * 0.49);
+        self.lowpass_coefficient = 1.0 - (-2.0 * PI * cutoff / self.sample_rate as f32).exp();
+    }
+}
+
+impl Default for Crossfeed {
+    fn default() -> Self {
+        Self::new(CrossfeedSettings::default())
+    }
+}
+
+impl Effect for Crossfeed {
+    fn initialize(&mut self, sample_rate: usize, channels: usize) {
+        self.sample_rate = sample_rate;
+        self.is_stereo = channels == 2;
+        self.update_coefficients();
+        self.reset();
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        if !self.is_stereo {
+            return;
+        }
+        for frame in samples.chunks_exact_mut(2) {
+            for (lowpass, sample) in self.lowpass.iter_mut().zip(frame.iter()) {
+                *lowpass += (sample - *lowpass) * self.lowpass_coefficient;
+            }
+            let [left, right] = self.lowpass;
+            frame[0] += self.feed * (right - left);
+            frame[1] += self.feed * (left - right);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.lowpass = [0.0; 2];
+    }
+}
+
+#[cfg(test)]
+#[path = "./crossfeed_test.rs"]
+mod crossfeed_test;
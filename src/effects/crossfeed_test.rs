@@ -0,0 +1,68 @@
+use std::f32::consts::PI;
+
+use super::Crossfeed;
+use crate::effects::Effect;
+
+// Interleaved stereo sine with separate left and right amplitudes
+fn stereo_sine(left: f32, right: f32, frequency: f32, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .flat_map(|i| {
+            let sample = (2.0 * PI * frequency * i as f32 / 48000.0).sin();
+            [left * sample, right * sample]
+        })
+        .collect()
+}
+
+// Peak levels of each channel over the second half of the signal
+fn channel_peaks(samples: &[f32]) -> (f32, f32) {
+    samples[samples.len() / 2..]
+        .chunks_exact(2)
+        .fold((0.0, 0.0), |(left, right), frame| {
+            (left.max(frame[0].abs()), right.max(frame[1].abs()))
+        })
+}
+
+#[test]
+fn centered_audio_is_unchanged() {
+    let mut crossfeed = Crossfeed::default();
+    crossfeed.initialize(48000, 2);
+    let input = stereo_sine(0.5, 0.5, 100.0, 4800);
+    let mut samples = input.clone();
+    crossfeed.process(&mut samples);
+
+    assert_eq!(input, samples);
+}
+
+#[test]
+fn hard_panned_bass_is_shared() {
+    let mut crossfeed = Crossfeed::default();
+    crossfeed.initialize(48000, 2);
+    let mut samples = stereo_sine(1.0, 0.0, 50.0, 48000);
+    crossfeed.process(&mut samples);
+
+    let (left, right) = channel_peaks(&samples);
+    assert!((20.0 * (left / right).log10() - 4.5).abs() < 0.5);
+}
+
+#[test]
+fn hard_panned_treble_is_mostly_separate() {
+    let mut crossfeed = Crossfeed::default();
+    crossfeed.initialize(48000, 2);
+    let mut samples = stereo_sine(1.0, 0.0, 10000.0, 48000);
+    crossfeed.process(&mut samples);
+
+    let (left, right) = channel_peaks(&samples);
+    assert!(left > 0.95);
+    assert!(right < 0.05);
+}
+
+#[test]
+fn bypassed_for_other_layouts() {
+    let mut crossfeed = Crossfeed::default();
+    crossfeed.initialize(48000, 6);
+    let input: Vec<f32> = (0..600).map(|i| (i % 6) as f32 / 6.0).collect();
+    let mut samples = input.clone();
+    crossfeed.process(&mut samples);
+
+    assert_eq!(input, samples);
+}
@@ -0,0 +1,124 @@
+use super::Effect;
+
+// Noise shaping filter designed by Lipshitz et al. for 44.1 kHz. It still works at higher
+// sample rates, although more of the noise ends up in the audible range.
+const LIPSHITZ_COEFFICIENTS: [f32; 5] = [2.033, -2.165, 1.959, -1.590, 0.6149];
+// Limits the error fed back so clipped samples can't make the filter unstable
+const MAX_ERROR_STEPS: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoiseShaping {
+    /// Plain TPDF dither with a flat noise spectrum.
+    #[default]
+    None,
+    /// Moves the noise towards high frequencies with a first order filter.
+    FirstOrder,
+    /// Moves the noise away from the frequencies where hearing is most sensitive.
+    Lipshitz,
+}
+
+impl NoiseShaping {
+    fn coefficients(&self) -> &'static [f32] {
+        match self {
+            Self::None => &[],
+            Self::FirstOrder => &[1.0],
+            Self::Lipshitz => &LIPSHITZ_COEFFICIENTS,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DitherSettings {
+    pub noise_shaping: NoiseShaping,
+}
+
+/// Rounds samples to a lower bit depth with TPDF dither so quantization produces a constant
+/// noise floor instead of distortion.
+#[derive(Clone, Debug)]
+pub struct Dither {
+    settings: DitherSettings,
+    // Size of the smallest step at the output bit depth
+    step: f32,
+    channels: usize,
+    rng_state: u32,
+    // Recent quantization errors for each channel, newest first
+    errors: Vec<[f32; LIPSHITZ_COEFFICIENTS.len()]>,
+}
+
+impl Dither {
+    pub fn new(settings: DitherSettings, bits: u32) -> Self {
+        Self {
+            settings,
+            step: 2f32.powi(1 - bits as i32),
+            channels: 0,
+            rng_state: 0x9e37_79b9,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &DitherSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: DitherSettings) {
+        self.settings = settings;
+        self.reset();
+    }
+
+    // Uniform random value between 0 and 1 using xorshift
+    fn next_random(&mut self) -> f32 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 17;
+        self.rng_state ^= self.rng_state << 5;
+        (self.rng_state >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+impl Effect for Dither {
+    fn initialize(&mut self, _sample_rate: usize, channels: usize) {
+        self.channels = channels;
+        self.errors = vec![Default::default(); channels];
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        // Digital silence stays silent rather than turning into noise
+        if samples.iter().all(|s| *s == 0.0) {
+            self.reset();
+            return;
+        }
+
+        let coefficients = self.settings.noise_shaping.coefficients();
+        let max = 1.0 - self.step;
+        let max_error = MAX_ERROR_STEPS * self.step;
+        for frame in samples.chunks_exact_mut(self.channels) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let errors = &self.errors[channel];
+                let shaped = *sample
+                    - coefficients
+                        .iter()
+                        .zip(errors)
+                        .map(|(coefficient, error)| coefficient * error)
+                        .sum::<f32>();
+                // The difference of two uniform values has a triangular distribution
+                let noise = (self.next_random() - self.next_random()) * self.step;
+                let quantized = ((shaped + noise) / self.step)
+                    .round()
+                    .clamp(-1.0 / self.step, max / self.step)
+                    * self.step;
+
+                let errors = &mut self.errors[channel];
+                errors.rotate_right(1);
+                errors[0] = (quantized - shaped).clamp(-max_error, max_error);
+                *sample = quantized;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.errors.fill(Default::default());
+    }
+}
+
+#[cfg(test)]
+#[path = "./dither_test.rs"]
+mod dither_test;
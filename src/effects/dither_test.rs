@@ -0,0 +1,60 @@
+use super::{Dither, DitherSettings, NoiseShaping};
+use crate::effects::Effect;
+
+const STEP: f32 = 1.0 / 32768.0;
+
+fn dithered(noise_shaping: NoiseShaping, input: &[f32]) -> Vec<f32> {
+    let mut dither = Dither::new(DitherSettings { noise_shaping }, 16);
+    dither.initialize(44100, 1);
+    let mut samples = input.to_vec();
+    dither.process(&mut samples);
+    samples
+}
+
+#[test]
+fn output_is_quantized() {
+    let input: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.01).sin() * 0.3).collect();
+    for noise_shaping in [NoiseShaping::None, NoiseShaping::Lipshitz] {
+        for sample in dithered(noise_shaping, &input) {
+            let steps = sample / STEP;
+            assert_eq!(steps.round(), steps);
+        }
+    }
+}
+
+#[test]
+fn preserves_levels_below_one_step() {
+    // Truncation would turn this into silence
+    let input = vec![0.25 * STEP; 100000];
+    let output = dithered(NoiseShaping::None, &input);
+    let mean = output.iter().sum::<f32>() / output.len() as f32;
+
+    assert!((mean / STEP - 0.25).abs() < 0.02);
+}
+
+#[test]
+fn silence_is_unchanged() {
+    let input = vec![0.0; 1000];
+    assert_eq!(input, dithered(NoiseShaping::Lipshitz, &input));
+}
+
+#[test]
+fn noise_shaping_reduces_low_frequency_noise() {
+    let input: Vec<f32> = (0..100000)
+        .map(|i| (i as f32 * 0.001).sin() * 0.1)
+        .collect();
+    // Energy of the error after a simple low pass filter
+    let low_frequency_noise = |output: Vec<f32>| {
+        let errors: Vec<f32> = output.iter().zip(&input).map(|(o, i)| o - i).collect();
+        errors
+            .windows(64)
+            .map(|w| (w.iter().sum::<f32>() / 64.0).powi(2))
+            .sum::<f32>()
+    };
+
+    let flat = low_frequency_noise(dithered(NoiseShaping::None, &input));
+    let first_order = low_frequency_noise(dithered(NoiseShaping::FirstOrder, &input));
+    let lipshitz = low_frequency_noise(dithered(NoiseShaping::Lipshitz, &input));
+    assert!(first_order < flat / 4.0);
+    assert!(lipshitz < flat / 4.0);
+}
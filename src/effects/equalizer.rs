@@ -0,0 +1,272 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use super::{BiquadCoefficients, BiquadState, Effect, FilterType};
+
+// Gain changes are spread over several blocks so moving a slider doesn't click
+const SMOOTHING_BLOCK_FRAMES: usize = 64;
+const MAX_GAIN_STEP_DB: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EqBand {
+    pub filter_type: FilterType,
+    pub frequency: f32,
+    /// Ignored by the low and high pass filters.
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+impl EqBand {
+    pub fn peaking(frequency: f32, gain_db: f32, q: f32) -> Self {
+        Self {
+            filter_type: FilterType::Peaking,
+            frequency,
+            gain_db,
+            q,
+        }
+    }
+
+    pub fn low_shelf(frequency: f32, gain_db: f32) -> Self {
+        Self {
+            filter_type: FilterType::LowShelf,
+            frequency,
+            gain_db,
+            q: FRAC_1_SQRT_2,
+        }
+    }
+
+    pub fn high_shelf(frequency: f32, gain_db: f32) -> Self {
+        Self {
+            filter_type: FilterType::HighShelf,
+            frequency,
+            gain_db,
+            q: FRAC_1_SQRT_2,
+        }
+    }
+
+    pub fn low_pass(frequency: f32, q: f32) -> Self {
+        Self {
+            filter_type: FilterType::LowPass,
+            frequency,
+            gain_db: 0.0,
+            q,
+        }
+    }
+
+    pub fn high_pass(frequency: f32, q: f32) -> Self {
+        Self {
+            filter_type: FilterType::HighPass,
+            frequency,
+            gain_db: 0.0,
+            q,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EqPreset {
+    Flat,
+    BassBoost,
+    TrebleBoost,
+    Vocal,
+    Loudness,
+}
+
+// Center frequencies of the bands used by the presets
+const PRESET_FREQUENCIES: [f32; 5] = [60.0, 250.0, 1000.0, 4000.0, 12000.0];
+
+impl EqPreset {
+    pub fn bands(&self) -> Vec<EqBand> {
+        let gains: [f32; 5] = match self {
+            Self::Flat => [0.0, 0.0, 0.0, 0.0, 0.0],
+            Self::BassBoost => [6.0, 3.0, 0.0, 0.0, 0.0],
+            Self::TrebleBoost => [0.0, 0.0, 0.0, 3.0, 6.0],
+            Self::Vocal => [-2.0, -1.0, 3.0, 2.0, 0.0],
+            Self::Loudness => [5.0, 1.0, -1.0, 1.0, 4.0],
+        };
+        let last = PRESET_FREQUENCIES.len() - 1;
+        PRESET_FREQUENCIES
+            .iter()
+            .zip(gains)
+            .enumerate()
+            .map(|(i, (frequency, gain_db))| match i {
+                0 => EqBand::low_shelf(*frequency, gain_db),
+                i if i == last => EqBand::high_shelf(*frequency, gain_db),
+                _ => EqBand::peaking(*frequency, gain_db, 1.0),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct BandFilter {
+    band: EqBand,
+    // The gain currently in use, which moves towards the band's gain over time
+    gain_db: f32,
+    coefficients: BiquadCoefficients,
+    states: Vec<BiquadState>,
+}
+
+impl BandFilter {
+    fn new(band: EqBand, sample_rate: usize, channels: usize) -> Self {
+        Self {
+            band,
+            gain_db: band.gain_db,
+            coefficients: coefficients(&band, band.gain_db, sample_rate),
+            states: vec![BiquadState::default(); channels],
+        }
+    }
+
+    fn is_smoothing(&self) -> bool {
+        self.gain_db != self.band.gain_db
+    }
+
+    fn step_gain(&mut self, sample_rate: usize) {
+        let difference = self.band.gain_db - self.gain_db;
+        self.gain_db += difference.clamp(-MAX_GAIN_STEP_DB, MAX_GAIN_STEP_DB);
+        self.coefficients = coefficients(&self.band, self.gain_db, sample_rate);
+    }
+}
+
+// The filters are calculated once the equalizer is initialized with the sample rate
+fn coefficients(band: &EqBand, gain_db: f32, sample_rate: usize) -> BiquadCoefficients {
+    if sample_rate == 0 {
+        return BiquadCoefficients::default();
+    }
+    BiquadCoefficients::new(
+        band.filter_type,
+        sample_rate,
+        band.frequency,
+        gain_db,
+        band.q,
+    )
+}
+
+/// A multi-band parametric equalizer.
+#[derive(Clone, Debug)]
+pub struct Equalizer {
+    filters: Vec<BandFilter>,
+    sample_rate: usize,
+    channels: usize,
+}
+
+impl Default for Equalizer {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Equalizer {
+    pub fn new(bands: Vec<EqBand>) -> Self {
+        Self {
+            filters: bands
+                .into_iter()
+                .map(|band| BandFilter::new(band, 0, 0))
+                .collect(),
+            sample_rate: 0,
+            channels: 0,
+        }
+    }
+
+    pub fn from_preset(preset: EqPreset) -> Self {
+        Self::new(preset.bands())
+    }
+
+    pub fn bands(&self) -> Vec<EqBand> {
+        self.filters.iter().map(|f| f.band).collect()
+    }
+
+    pub fn band(&self, index: usize) -> Option<&EqBand> {
+        self.filters.get(index).map(|f| &f.band)
+    }
+
+    pub fn add_band(&mut self, band: EqBand) -> usize {
+        self.filters
+            .push(BandFilter::new(band, self.sample_rate, self.channels));
+        self.filters.len() - 1
+    }
+
+    pub fn remove_band(&mut self, index: usize) -> Option<EqBand> {
+        (index < self.filters.len()).then(|| self.filters.remove(index).band)
+    }
+
+    /// Updates a band. Gain changes are applied gradually while changes to the frequency, Q or
+    /// filter type take effect immediately.
+    pub fn set_band(&mut self, index: usize, band: EqBand) {
+        if let Some(filter) = self.filters.get_mut(index) {
+            filter.band = band;
+            filter.coefficients = coefficients(&band, filter.gain_db, self.sample_rate);
+        }
+    }
+
+    pub fn set_band_gain(&mut self, index: usize, gain_db: f32) {
+        if let Some(filter) = self.filters.get_mut(index) {
+            filter.band.gain_db = gain_db;
+        }
+    }
+
+    /// Switches to the preset's bands. If the current bands use the same filters, only the gains
+    /// are changed so the transition is smooth.
+    pub fn set_preset(&mut self, preset: EqPreset) {
+        let bands = preset.bands();
+        let is_compatible = bands.len() == self.filters.len()
+            && bands.iter().zip(&self.filters).all(|(band, filter)| {
+                band.filter_type == filter.band.filter_type
+                    && band.frequency == filter.band.frequency
+                    && band.q == filter.band.q
+            });
+        if is_compatible {
+            for (index, band) in bands.into_iter().enumerate() {
+                self.set_band_gain(index, band.gain_db);
+            }
+        } else {
+            *self = Self {
+                sample_rate: self.sample_rate,
+                channels: self.channels,
+                ..Self::new(Vec::new())
+            };
+            for band in bands {
+                self.add_band(band);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+}
+
+impl Effect for Equalizer {
+    fn initialize(&mut self, sample_rate: usize, channels: usize) {
+        self.sample_rate = sample_rate;
+        self.channels = channels;
+        for filter in &mut self.filters {
+            *filter = BandFilter::new(filter.band, sample_rate, channels);
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.channels;
+        for block in samples.chunks_mut(SMOOTHING_BLOCK_FRAMES * channels) {
+            for filter in &mut self.filters {
+                if filter.is_smoothing() {
+                    filter.step_gain(self.sample_rate);
+                }
+                for frame in block.chunks_exact_mut(channels) {
+                    for (sample, state) in frame.iter_mut().zip(&mut filter.states) {
+                        *sample = state.process(&filter.coefficients, *sample);
+                    }
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.states.fill(BiquadState::default());
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./equalizer_test.rs"]
+mod equalizer_test;
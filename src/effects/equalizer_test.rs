@@ -0,0 +1,76 @@
+use std::f32::consts::PI;
+
+use super::{EqBand, EqPreset, Equalizer};
+use crate::effects::Effect;
+
+fn sine(frequency: f32, sample_rate: usize, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|i| 0.25 * (2.0 * PI * frequency * i as f32 / sample_rate as f32).sin())
+        .collect()
+}
+
+// Peak level of the second half of the signal, after the filter has settled
+fn peak(samples: &[f32]) -> f32 {
+    samples[samples.len() / 2..]
+        .iter()
+        .fold(0.0, |peak, s| s.abs().max(peak))
+}
+
+#[test]
+fn peaking_boost() {
+    let mut eq = Equalizer::new(vec![EqBand::peaking(1000.0, 6.0, 1.0)]);
+    eq.initialize(48000, 1);
+    let mut samples = sine(1000.0, 48000, 48000);
+    eq.process(&mut samples);
+
+    assert!((peak(&samples) / 0.25 - 1.995).abs() < 0.02);
+}
+
+#[test]
+fn low_pass_attenuates() {
+    let mut eq = Equalizer::new(vec![EqBand::low_pass(500.0, 0.707)]);
+    eq.initialize(44100, 1);
+    let mut samples = sine(8000.0, 44100, 44100);
+    eq.process(&mut samples);
+
+    assert!(peak(&samples) < 0.25 * 0.01);
+}
+
+#[test]
+fn gain_changes_are_smoothed() {
+    let mut eq = Equalizer::new(vec![EqBand::peaking(1000.0, 0.0, 1.0)]);
+    eq.initialize(48000, 1);
+    eq.set_band_gain(0, 12.0);
+    let mut samples = sine(1000.0, 48000, 64);
+    eq.process(&mut samples);
+
+    // Only the first step has been applied
+    assert_eq!(0.25, eq.filters[0].gain_db);
+    assert_eq!(12.0, eq.band(0).unwrap().gain_db);
+
+    let mut samples = sine(1000.0, 48000, 48000);
+    eq.process(&mut samples);
+    assert_eq!(12.0, eq.filters[0].gain_db);
+}
+
+#[test]
+fn sample_rate_change() {
+    let mut eq = Equalizer::new(vec![EqBand::peaking(1000.0, 6.0, 1.0)]);
+    eq.initialize(44100, 1);
+    eq.initialize(96000, 1);
+    let mut samples = sine(1000.0, 96000, 96000);
+    eq.process(&mut samples);
+
+    assert!((peak(&samples) / 0.25 - 1.995).abs() < 0.02);
+}
+
+#[test]
+fn presets() {
+    let mut eq = Equalizer::from_preset(EqPreset::Flat);
+    assert_eq!(5, eq.bands().len());
+
+    eq.set_preset(EqPreset::BassBoost);
+    assert_eq!(6.0, eq.band(0).unwrap().gain_db);
+    // The gain is still smoothed when switching between compatible presets
+    assert_eq!(0.0, eq.filters[0].gain_db);
+}
@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::{Effect, TRUE_PEAK_DELAY, TruePeakDetector};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LimiterSettings {
+    /// The maximum output level in dBTP.
+    pub ceiling_db: f32,
+    /// How long the gain takes to recover after a peak.
+    pub release: Duration,
+    /// How far ahead the limiter looks for peaks. The output is delayed by this amount.
+    pub lookahead: Duration,
+}
+
+impl Default for LimiterSettings {
+    fn default() -> Self {
+        Self {
+            ceiling_db: -1.0,
+            release: Duration::from_millis(100),
+            lookahead: Duration::from_millis(5),
+        }
+    }
+}
+
+/// A lookahead limiter that keeps the true peak level of the output below a ceiling.
+#[derive(Clone, Debug)]
+pub struct Limiter {
+    settings: LimiterSettings,
+    sample_rate: usize,
+    channels: usize,
+    ceiling: f32,
+    release_coefficient: f32,
+    peak_detectors: Vec<TruePeakDetector>,
+    delay: VecDeque<f32>,
+    // Ascending minimums of the required gain over the lookahead window
+    window: VecDeque<(u64, f32)>,
+    window_frames: u64,
+    frame: u64,
+    release_gain: f32,
+    // Moving average of the released gain so reductions start gradually
+    smoothing: VecDeque<f32>,
+    smoothing_sum: f64,
+    gain: f32,
+}
+
+impl Limiter {
+    pub fn new(settings: LimiterSettings) -> Self {
+        Self {
+            settings,
+            sample_rate: 0,
+            channels: 0,
+            ceiling: 1.0,
+            release_coefficient: 1.0,
+            peak_detectors: Vec::new(),
+            delay: VecDeque::new(),
+            window: VecDeque::new(),
+            window_frames: 1,
+            frame: 0,
+            release_gain: 1.0,
+            smoothing: VecDeque::new(),
+            smoothing_sum: 0.0,
+            gain: 1.0,
+        }
+    }
+
+    pub fn settings(&self) -> &LimiterSettings {
+        &self.settings
+    }
+
+    /// Changing the lookahead clears any audio that's waiting to be output.
+    pub fn set_settings(&mut self, settings: LimiterSettings) {
+        let lookahead_changed = settings.lookahead != self.settings.lookahead;
+        self.settings = settings;
+        if lookahead_changed {
+            self.initialize(self.sample_rate, self.channels);
+        } else {
+            self.update_coefficients();
+        }
+    }
+
+    /// The amount the output is currently being attenuated by, in dB.
+    pub fn gain_reduction_db(&self) -> f32 {
+        -20.0 * self.gain.log10()
+    }
+
+    fn lookahead_frames(&self) -> usize {
+        (self.settings.lookahead.as_secs_f64() * self.sample_rate as f64).round() as usize
+    }
+
+    fn update_coefficients(&mut self) {
+        self.ceiling = 10f32.powf(self.settings.ceiling_db / 20.0);
+        let release_frames = self.settings.release.as_secs_f32() * self.sample_rate as f32;
+        self.release_coefficient = if release_frames > 0.0 {
+            1.0 - (-1.0 / release_frames).exp()
+        } else {
+            1.0
+        };
+    }
+
+    // Returns the highest sample or interpolated peak in the frame
+    fn detect_peak(&mut self, frame: &[f32]) -> f32 {
+        frame
+            .iter()
+            .zip(&mut self.peak_detectors)
+            .map(|(sample, detector)| detector.process(*sample))
+            .fold(0.0, f32::max)
+    }
+
+    fn next_gain(&mut self, required: f32) -> f32 {
+        while self
+            .window
+            .back()
+            .is_some_and(|(_, gain)| *gain >= required)
+        {
+            self.window.pop_back();
+        }
+        self.window.push_back((self.frame, required));
+        while self
+            .window
+            .front()
+            .is_some_and(|(frame, _)| frame + self.window_frames <= self.frame)
+        {
+            self.window.pop_front();
+        }
+        self.frame += 1;
+
+        let target = self.window.front().map_or(1.0, |(_, gain)| *gain);
+        self.release_gain = if target < self.release_gain {
+            target
+        } else {
+            self.release_gain + (target - self.release_gain) * self.release_coefficient
+        };
+
+        self.smoothing.push_back(self.release_gain);
+        self.smoothing_sum += self.release_gain as f64;
+        if let Some(oldest) = self.smoothing.pop_front() {
+            self.smoothing_sum -= oldest as f64;
+        }
+        (self.smoothing_sum / self.smoothing.len() as f64) as f32
+    }
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self::new(LimiterSettings::default())
+    }
+}
+
+impl Effect for Limiter {
+    fn initialize(&mut self, sample_rate: usize, channels: usize) {
+        self.sample_rate = sample_rate;
+        self.channels = channels;
+        self.update_coefficients();
+        self.peak_detectors = vec![TruePeakDetector::new(); channels];
+        self.reset();
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let peak = self.detect_peak(frame);
+            let required = if peak > self.ceiling {
+                self.ceiling / peak
+            } else {
+                1.0
+            };
+            self.gain = self.next_gain(required);
+
+            self.delay.extend(frame.iter().copied());
+            for sample in frame {
+                let delayed = self.delay.pop_front().unwrap_or_default();
+                *sample = delayed * self.gain;
+            }
+        }
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) {
+        // Pushing silence through outputs everything in the delay line
+        let mut tail = vec![0.0; self.delay.len()];
+        self.process(&mut tail);
+        output.extend(tail);
+    }
+
+    fn reset(&mut self) {
+        // The gain for each sample is the average of the gains needed over the following
+        // lookahead window, which is always low enough for the loudest of them
+        let lookahead = self.lookahead_frames();
+        let delay_frames = lookahead + TRUE_PEAK_DELAY;
+        for detector in &mut self.peak_detectors {
+            detector.reset();
+        }
+        self.delay.clear();
+        self.delay.resize(delay_frames * self.channels, 0.0);
+        self.window.clear();
+        self.window_frames = delay_frames as u64 + 1;
+        self.frame = 0;
+        self.release_gain = 1.0;
+        self.smoothing.clear();
+        self.smoothing.resize(lookahead + 1, 1.0);
+        self.smoothing_sum = self.smoothing.len() as f64;
+        self.gain = 1.0;
+    }
+}
+
+#[cfg(test)]
+#[path = "./limiter_test.rs"]
+mod limiter_test;
@@ -0,0 +1,115 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use super::{Limiter, LimiterSettings};
+use crate::effects::{Effect, TRUE_PEAK_DELAY};
+
+fn sine(amplitude: f32, frequency: f32, sample_rate: usize, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|i| amplitude * (2.0 * PI * frequency * i as f32 / sample_rate as f32).sin())
+        .collect()
+}
+
+fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0, |peak, s| s.abs().max(peak))
+}
+
+#[test]
+fn quiet_audio_is_delayed_but_unchanged() {
+    let mut limiter = Limiter::default();
+    limiter.initialize(48000, 1);
+    let input = sine(0.5, 440.0, 48000, 4800);
+    let mut samples = input.clone();
+    limiter.process(&mut samples);
+
+    let delay = 240 + TRUE_PEAK_DELAY;
+    assert_eq!(&input[..input.len() - delay], &samples[delay..]);
+    assert_eq!(0.0, limiter.gain_reduction_db());
+}
+
+#[test]
+fn loud_audio_stays_below_ceiling() {
+    let mut limiter = Limiter::default();
+    limiter.initialize(48000, 2);
+    let mut samples = sine(2.0, 997.0, 48000, 96000);
+    limiter.process(&mut samples);
+
+    let ceiling = 10f32.powf(-1.0 / 20.0);
+    assert!(peak(&samples) <= ceiling);
+    assert!((limiter.gain_reduction_db() - 7.0).abs() < 0.2);
+}
+
+#[test]
+fn sudden_peak_is_anticipated() {
+    let mut limiter = Limiter::default();
+    limiter.initialize(44100, 1);
+    let mut samples = vec![0.1; 4410];
+    samples[2000] = 4.0;
+    samples[2001] = -4.0;
+    limiter.process(&mut samples);
+
+    let ceiling = 10f32.powf(-1.0 / 20.0);
+    assert!(peak(&samples) <= ceiling);
+    // The reduction ramps in rather than jumping down at the peak
+    let delay = 221 + TRUE_PEAK_DELAY;
+    assert!(samples[2000 + delay - 100] < 0.1);
+    assert!(samples[2000 + delay - 100] > samples[2000 + delay - 10]);
+}
+
+#[test]
+fn gain_recovers_after_release() {
+    let mut limiter = Limiter::new(LimiterSettings {
+        release: Duration::from_millis(50),
+        ..Default::default()
+    });
+    limiter.initialize(48000, 1);
+    let mut samples = sine(2.0, 997.0, 48000, 4800);
+    limiter.process(&mut samples);
+    assert!(limiter.gain_reduction_db() > 6.0);
+
+    let mut samples = sine(0.5, 997.0, 48000, 48000);
+    limiter.process(&mut samples);
+    assert!(limiter.gain_reduction_db() < 0.01);
+}
+
+#[test]
+fn flush_outputs_delayed_audio() {
+    let mut limiter = Limiter::default();
+    limiter.initialize(48000, 2);
+    let input = sine(0.5, 440.0, 48000, 9600);
+    let mut samples = input.clone();
+    limiter.process(&mut samples);
+    limiter.flush(&mut samples);
+
+    let delay = (240 + TRUE_PEAK_DELAY) * 2;
+    assert_eq!(input.len() + delay, samples.len());
+    assert_eq!(input, samples[delay..]);
+}
+
+#[test]
+fn flushed_audio_is_limited() {
+    let mut limiter = Limiter::default();
+    limiter.initialize(48000, 1);
+    let mut samples = sine(2.0, 997.0, 48000, 4800);
+    limiter.process(&mut samples);
+    let mut tail = Vec::new();
+    limiter.flush(&mut tail);
+
+    let ceiling = 10f32.powf(-1.0 / 20.0);
+    assert_eq!(240 + TRUE_PEAK_DELAY, tail.len());
+    assert!(peak(&tail) <= ceiling);
+    assert!(peak(&tail) > ceiling * 0.9);
+}
+
+#[test]
+fn reset_clears_delayed_audio() {
+    let mut limiter = Limiter::default();
+    limiter.initialize(48000, 1);
+    let mut samples = vec![0.5; 100];
+    limiter.process(&mut samples);
+    limiter.reset();
+
+    let mut samples = vec![0.0; 400];
+    limiter.process(&mut samples);
+    assert_eq!(0.0, peak(&samples));
+}
@@ -0,0 +1,37 @@
+mod biquad;
+pub use biquad::*;
+mod chain;
+pub use chain::*;
+mod channel_controls;
+pub use channel_controls::*;
+mod crossfeed;
+pub use crossfeed::*;
+mod dither;
+pub use dither::*;
+mod equalizer;
+pub use equalizer::*;
+mod limiter;
+pub use limiter::*;
+mod time_stretch;
+pub use time_stretch::*;
+mod true_peak;
+pub(crate) use true_peak::*;
+mod vocal_remover;
+pub use vocal_remover::*;
+
+/// Audio processing applied to the output before it's written to the device.
+pub trait Effect: Send {
+    /// Called before any audio is processed and whenever the output format changes.
+    fn initialize(&mut self, sample_rate: usize, channels: usize);
+
+    /// Processes interleaved samples in place.
+    fn process(&mut self, samples: &mut [f32]);
+
+    /// Clears any internal state, such as filter history, so audio from before a seek doesn't
+    /// bleed into the new position.
+    fn reset(&mut self);
+
+    /// Appends any audio the effect is still holding back, such as a lookahead delay, to the
+    /// output. This is called at the end of the stream.
+    fn flush(&mut self, _output: &mut Vec<f32>) {}
+}
@@ -0,0 +1,232 @@
+use std::f32::consts::PI;
+
+pub const MIN_PLAYBACK_SPEED: f32 = 0.5;
+pub const MAX_PLAYBACK_SPEED: f32 = 3.0;
+
+const SEGMENT_TIME_SECS: f64 = 0.03;
+// How far each segment can be moved from its nominal position to line up with the previous one
+const SEARCH_TIME_SECS: f64 = 0.0075;
+// Offsets are first compared at this interval and then refined around the best match
+const COARSE_SEARCH_STEP: usize = 4;
+
+/// Changes the tempo of audio without changing its pitch using WSOLA (waveform similarity
+/// overlap-add). Overlapping segments of the input are joined where their waveforms line up best,
+/// taking segments further apart to speed up and closer together to slow down.
+#[derive(Clone, Debug)]
+pub struct TimeStretch {
+    speed: f32,
+    channels: usize,
+    segment_len: usize,
+    hop: usize,
+    search_len: usize,
+    window: Vec<f32>,
+    is_stretching: bool,
+    // Interleaved input that hasn't been used yet and its mono downmix used for matching
+    input: Vec<f32>,
+    mono: Vec<f32>,
+    // Position of the next segment before it's lined up, in frames from the start of the input
+    nominal: f64,
+    // Start of the input that naturally follows the last segment
+    continuation: usize,
+    // Faded out second half of the last segment
+    overlap: Vec<f32>,
+}
+
+impl Default for TimeStretch {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            channels: 0,
+            segment_len: 0,
+            hop: 0,
+            search_len: 0,
+            window: Vec::new(),
+            is_stretching: false,
+            input: Vec::new(),
+            mono: Vec::new(),
+            nominal: 0.0,
+            continuation: 0,
+            overlap: Vec::new(),
+        }
+    }
+}
+
+impl TimeStretch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn initialize(&mut self, sample_rate: usize, channels: usize) {
+        self.channels = channels;
+        self.segment_len = ((SEGMENT_TIME_SECS * sample_rate as f64) as usize / 2 * 2).max(2);
+        self.hop = self.segment_len / 2;
+        self.search_len = (SEARCH_TIME_SECS * sample_rate as f64) as usize;
+        // Periodic Hann window, which sums to one when overlapped by half its length
+        self.window = (0..self.segment_len)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / self.segment_len as f32).cos())
+            .collect();
+        self.reset();
+    }
+
+    /// Sets the playback speed, which is limited to between [`MIN_PLAYBACK_SPEED`] and
+    /// [`MAX_PLAYBACK_SPEED`].
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Whether the speed has been changed or audio from an earlier speed change is still being
+    /// played out.
+    pub fn is_active(&self) -> bool {
+        self.speed != 1.0 || self.is_stretching
+    }
+
+    /// Stretches interleaved samples and appends the result to the output. Some of the input is
+    /// held back until enough is available to find where the next segment fits.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        if !self.is_active() {
+            output.extend_from_slice(input);
+            return;
+        }
+
+        self.input.extend_from_slice(input);
+        self.mono.extend(
+            input
+                .chunks_exact(self.channels)
+                .map(|frame| frame.iter().sum::<f32>()),
+        );
+        if self.speed == 1.0 {
+            // The input after the last segment joins it seamlessly, so normal playback can
+            // continue from there
+            self.flush(output);
+            return;
+        }
+
+        if !self.is_stretching {
+            self.is_stretching = true;
+            // Start as if a previous segment ended at the beginning of the input so the audio
+            // isn't faded in
+            self.continuation = 0;
+            self.nominal = self.hop as f64 * (self.speed as f64 - 1.0);
+        }
+        while self.next_segment(output) {}
+    }
+
+    /// Outputs any audio that's being held back and stops stretching until more input arrives.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        if self.is_stretching {
+            output.extend_from_slice(&self.input[self.continuation * self.channels..]);
+        } else {
+            output.extend_from_slice(&self.input);
+        }
+        self.reset();
+    }
+
+    /// Discards any audio that's being held back.
+    pub fn reset(&mut self) {
+        self.is_stretching = false;
+        self.input.clear();
+        self.mono.clear();
+        self.nominal = 0.0;
+        self.continuation = 0;
+        self.overlap.clear();
+    }
+
+    // Adds the next segment to the output. Returns false if more input is needed.
+    fn next_segment(&mut self, output: &mut Vec<f32>) -> bool {
+        let channels = self.channels;
+        let nominal = self.nominal.round().max(0.0) as usize;
+        let search_start = nominal.saturating_sub(self.search_len);
+        let search_end = nominal + self.search_len;
+        let frames = self.mono.len();
+        if search_end + self.segment_len > frames || self.continuation + self.segment_len > frames {
+            return false;
+        }
+
+        if self.overlap.is_empty() {
+            self.overlap = self.input
+                [self.continuation * channels..(self.continuation + self.hop) * channels]
+                .chunks_exact(channels)
+                .zip(&self.window[self.hop..])
+                .flat_map(|(frame, w)| frame.iter().map(move |s| s * w))
+                .collect();
+        }
+
+        let start = self.best_match(search_start, search_end);
+        let segment = &self.input[start * channels..(start + self.segment_len) * channels];
+        let (first_half, second_half) = segment.split_at(self.hop * channels);
+        output.extend(
+            first_half
+                .chunks_exact(channels)
+                .zip(&self.window[..self.hop])
+                .flat_map(|(frame, w)| frame.iter().map(move |s| s * w))
+                .zip(&self.overlap)
+                .map(|(s, overlap)| s + overlap),
+        );
+        self.overlap.clear();
+        self.overlap.extend(
+            second_half
+                .chunks_exact(channels)
+                .zip(&self.window[self.hop..])
+                .flat_map(|(frame, w)| frame.iter().map(move |s| s * w)),
+        );
+
+        self.continuation = start + self.hop;
+        self.nominal += self.hop as f64 * self.speed as f64;
+
+        // Drop input that can no longer be used
+        let used = self
+            .continuation
+            .min((self.nominal.max(0.0) as usize).saturating_sub(self.search_len));
+        self.input.drain(..used * channels);
+        self.mono.drain(..used);
+        self.continuation -= used;
+        self.nominal -= used as f64;
+        true
+    }
+
+    // Finds the segment start within the range whose waveform is most similar to the input that
+    // follows the last segment
+    fn best_match(&self, search_start: usize, search_end: usize) -> usize {
+        let coarse = (search_start..=search_end).step_by(COARSE_SEARCH_STEP);
+        let coarse_start = self.most_similar(coarse, 2);
+        let fine = coarse_start
+            .saturating_sub(COARSE_SEARCH_STEP - 1)
+            .max(search_start)
+            ..=(coarse_start + COARSE_SEARCH_STEP - 1).min(search_end);
+        self.most_similar(fine, 1)
+    }
+
+    fn most_similar(&self, starts: impl Iterator<Item = usize>, stride: usize) -> usize {
+        let mut best = (0, f32::MIN);
+        for start in starts {
+            let similarity = self.similarity(start, stride);
+            if similarity > best.1 {
+                best = (start, similarity);
+            }
+        }
+        best.0
+    }
+
+    // Normalized cross-correlation between the candidate segment and the continuation
+    fn similarity(&self, start: usize, stride: usize) -> f32 {
+        let (correlation, energy) = self.mono[start..start + self.segment_len]
+            .iter()
+            .zip(&self.mono[self.continuation..])
+            .step_by(stride)
+            .fold((0.0, 0.0), |(correlation, energy), (candidate, target)| {
+                (
+                    correlation + candidate * target,
+                    energy + candidate * candidate,
+                )
+            });
+        correlation / (energy + f32::EPSILON).sqrt()
+    }
+}
+
+#[cfg(test)]
+#[path = "./time_stretch_test.rs"]
+mod time_stretch_test;
@@ -0,0 +1,99 @@
+use std::f32::consts::PI;
+
+use super::TimeStretch;
+
+fn sine(frequency: f32, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|i| 0.5 * (2.0 * PI * frequency * i as f32 / 48000.0).sin())
+        .collect()
+}
+
+// Stretches the input in chunks like the output path does
+fn stretch(time_stretch: &mut TimeStretch, input: &[f32]) -> Vec<f32> {
+    let mut output = Vec::new();
+    for chunk in input.chunks(1024) {
+        time_stretch.process(chunk, &mut output);
+    }
+    output
+}
+
+fn mono_time_stretch(speed: f32) -> TimeStretch {
+    let mut time_stretch = TimeStretch::new();
+    time_stretch.initialize(48000, 1);
+    time_stretch.set_speed(speed);
+    time_stretch
+}
+
+fn max_step(samples: &[f32]) -> f32 {
+    samples
+        .windows(2)
+        .map(|w| (w[1] - w[0]).abs())
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn normal_speed_is_unchanged() {
+    let mut time_stretch = mono_time_stretch(1.0);
+    let input = sine(440.0, 48000);
+    assert!(!time_stretch.is_active());
+    assert_eq!(input, stretch(&mut time_stretch, &input));
+}
+
+#[test]
+fn length_changes_with_speed() {
+    for speed in [0.5, 1.5, 3.0] {
+        let mut time_stretch = mono_time_stretch(speed);
+        let input = sine(440.0, 96000);
+        let mut output = stretch(&mut time_stretch, &input);
+        time_stretch.flush(&mut output);
+        // The held back input is flushed at normal speed
+        let expected = input.len() as f32 / speed;
+        assert!((output.len() as f32 - expected).abs() < 48000.0 * 0.05);
+    }
+}
+
+#[test]
+fn pitch_is_preserved() {
+    let mut time_stretch = mono_time_stretch(2.0);
+    let input = sine(440.0, 96000);
+    let output = stretch(&mut time_stretch, &input);
+
+    let zero_crossings = output
+        .windows(2)
+        .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+        .count();
+    let frequency = zero_crossings as f32 / (output.len() as f32 / 48000.0);
+    assert!((frequency - 440.0).abs() < 5.0);
+}
+
+#[test]
+fn output_is_continuous() {
+    // The largest step between samples of the sine itself
+    let limit = 0.5 * 2.0 * PI * 440.0 / 48000.0 * 1.1;
+
+    let mut time_stretch = mono_time_stretch(1.5);
+    let input = sine(440.0, 96000);
+    let mut output = stretch(&mut time_stretch, &input[..48000]);
+    // Returning to normal speed continues from where stretching left off
+    time_stretch.set_speed(1.0);
+    output.extend(stretch(&mut time_stretch, &input[48000..]));
+
+    assert!(max_step(&output) < limit);
+    assert!(!time_stretch.is_active());
+}
+
+#[test]
+fn stereo() {
+    let mut time_stretch = TimeStretch::new();
+    time_stretch.initialize(48000, 2);
+    time_stretch.set_speed(0.75);
+    let input: Vec<f32> = sine(440.0, 48000)
+        .into_iter()
+        .flat_map(|s| [s, -s])
+        .collect();
+    let mut output = stretch(&mut time_stretch, &input);
+    time_stretch.flush(&mut output);
+
+    assert_eq!(0, output.len() % 2);
+    assert!(output.chunks_exact(2).all(|frame| frame[0] == -frame[1]));
+}
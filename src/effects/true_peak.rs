@@ -0,0 +1,79 @@
+use std::f32::consts::PI;
+
+const OVERSAMPLING: usize = 4;
+const TAPS_PER_PHASE: usize = 12;
+// Peaks between samples are only detected once the interpolation filter has seen the samples
+// after them
+pub(crate) const TRUE_PEAK_DELAY: usize = TAPS_PER_PHASE / 2;
+
+// Measures the level of a single channel including the peaks between samples, using 4x
+// oversampling as described in ITU-R BS.1770
+#[derive(Clone, Debug)]
+pub(crate) struct TruePeakDetector {
+    // Interpolation filter taps split by output phase
+    phases: Vec<Vec<f32>>,
+    history: Vec<f32>,
+    pos: usize,
+}
+
+impl TruePeakDetector {
+    pub(crate) fn new() -> Self {
+        let filter = interpolation_filter();
+        let phases = (0..OVERSAMPLING)
+            .map(|phase| {
+                filter
+                    .iter()
+                    .skip(phase)
+                    .step_by(OVERSAMPLING)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        Self {
+            phases,
+            history: vec![0.0; TAPS_PER_PHASE],
+            pos: 0,
+        }
+    }
+
+    // Returns the highest of the sample and the interpolated peaks
+    pub(crate) fn process(&mut self, sample: f32) -> f32 {
+        self.history[self.pos] = sample;
+        let mut peak = sample.abs();
+        for phase in &self.phases {
+            let interpolated: f32 = phase
+                .iter()
+                .enumerate()
+                .map(|(i, tap)| {
+                    tap * self.history[(self.pos + TAPS_PER_PHASE - i) % TAPS_PER_PHASE]
+                })
+                .sum();
+            peak = peak.max(interpolated.abs());
+        }
+        self.pos = (self.pos + 1) % TAPS_PER_PHASE;
+        peak
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.pos = 0;
+    }
+}
+
+// Windowed sinc filter used to interpolate between samples
+fn interpolation_filter() -> Vec<f32> {
+    let len = OVERSAMPLING * TAPS_PER_PHASE;
+    let center = (len - 1) as f32 / 2.0;
+    (0..len)
+        .map(|n| {
+            let x = (n as f32 - center) / OVERSAMPLING as f32;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let window = 0.5 - 0.5 * (2.0 * PI * n as f32 / (len - 1) as f32).cos();
+            sinc * window
+        })
+        .collect()
+}
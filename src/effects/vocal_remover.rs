@@ -0,0 +1,152 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::time::Duration;
+
+use super::{BiquadCoefficients, BiquadState, Effect, FilterType};
+
+// Toggling the effect or changing its strength fades over this time
+const RAMP_TIME: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VocalRemoverSettings {
+    /// How much of the center-panned audio to remove, from 0 to 1.
+    pub strength: f32,
+    /// Center-panned audio below this frequency, such as bass and kick drums, is kept.
+    pub preserve_below: f32,
+}
+
+impl Default for VocalRemoverSettings {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            preserve_below: 150.0,
+        }
+    }
+}
+
+/// Reduces vocals by cancelling audio that's panned to the center of stereo sources.
+///
+/// Only stereo output is processed, and mono sources are left alone since all of their audio is
+/// in the center.
+#[derive(Clone, Debug)]
+pub struct VocalRemover {
+    settings: VocalRemoverSettings,
+    is_enabled: bool,
+    is_stereo: bool,
+    is_mono_source: bool,
+    sample_rate: usize,
+    ramp_frames: usize,
+    strength: f32,
+    lowpass: BiquadCoefficients,
+    lowpass_state: BiquadState,
+}
+
+impl VocalRemover {
+    pub fn new(settings: VocalRemoverSettings) -> Self {
+        Self {
+            settings,
+            is_enabled: false,
+            is_stereo: false,
+            is_mono_source: false,
+            sample_rate: 0,
+            ramp_frames: 0,
+            strength: 0.0,
+            // Calculated once the sample rate is known
+            lowpass: BiquadCoefficients::default(),
+            lowpass_state: BiquadState::default(),
+        }
+    }
+
+    pub fn settings(&self) -> &VocalRemoverSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: VocalRemoverSettings) {
+        if self.sample_rate > 0 {
+            self.lowpass = lowpass(&settings, self.sample_rate);
+        }
+        self.settings = settings;
+    }
+
+    pub fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    /// Tells the vocal remover how many channels the source has so mono sources that were
+    /// upmixed to stereo aren't cancelled out.
+    pub fn set_source_channels(&mut self, channels: usize) {
+        self.is_mono_source = channels < 2;
+    }
+
+    /// Whether vocals are being removed from the output, including while the effect fades in or
+    /// out.
+    pub fn is_active(&self) -> bool {
+        self.is_stereo && (self.strength > 0.0 || self.target_strength() > 0.0)
+    }
+
+    fn target_strength(&self) -> f32 {
+        if self.is_enabled && !self.is_mono_source {
+            self.settings.strength.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+fn lowpass(settings: &VocalRemoverSettings, sample_rate: usize) -> BiquadCoefficients {
+    BiquadCoefficients::new(
+        FilterType::LowPass,
+        sample_rate,
+        settings.preserve_below,
+        0.0,
+        FRAC_1_SQRT_2,
+    )
+}
+
+impl Default for VocalRemover {
+    fn default() -> Self {
+        Self::new(VocalRemoverSettings::default())
+    }
+}
+
+impl Effect for VocalRemover {
+    fn initialize(&mut self, sample_rate: usize, channels: usize) {
+        self.sample_rate = sample_rate;
+        self.is_stereo = channels == 2;
+        self.ramp_frames = ((RAMP_TIME.as_secs_f64() * sample_rate as f64).round() as usize).max(1);
+        self.lowpass = lowpass(&self.settings, sample_rate);
+        self.reset();
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        if !self.is_active() {
+            return;
+        }
+        let target = self.target_strength();
+        let step = 1.0 / self.ramp_frames as f32;
+        for frame in samples.chunks_exact_mut(2) {
+            self.strength = if self.strength < target {
+                (self.strength + step).min(target)
+            } else {
+                (self.strength - step).max(target)
+            };
+            // The low frequencies are subtracted from the center signal so they're kept
+            let center = (frame[0] + frame[1]) / 2.0;
+            let center_low = self.lowpass_state.process(&self.lowpass, center);
+            let removed = self.strength * (center - center_low);
+            frame[0] -= removed;
+            frame[1] -= removed;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.lowpass_state = BiquadState::default();
+    }
+}
+
+#[cfg(test)]
+#[path = "./vocal_remover_test.rs"]
+mod vocal_remover_test;
@@ -0,0 +1,90 @@
+use std::f32::consts::PI;
+
+use super::VocalRemover;
+use crate::effects::Effect;
+
+// Interleaved stereo sine with separate left and right amplitudes
+fn stereo_sine(left: f32, right: f32, frequency: f32, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .flat_map(|i| {
+            let sample = (2.0 * PI * frequency * i as f32 / 48000.0).sin();
+            [left * sample, right * sample]
+        })
+        .collect()
+}
+
+// Peak levels of each channel over the second half of the signal
+fn channel_peaks(samples: &[f32]) -> (f32, f32) {
+    samples[samples.len() / 2..]
+        .chunks_exact(2)
+        .fold((0.0, 0.0), |(left, right), frame| {
+            (left.max(frame[0].abs()), right.max(frame[1].abs()))
+        })
+}
+
+fn enabled_vocal_remover() -> VocalRemover {
+    let mut vocal_remover = VocalRemover::default();
+    vocal_remover.initialize(48000, 2);
+    vocal_remover.set_enabled(true);
+    vocal_remover
+}
+
+#[test]
+fn center_is_removed() {
+    let mut vocal_remover = enabled_vocal_remover();
+    let mut samples = stereo_sine(0.5, 0.5, 2000.0, 48000);
+    vocal_remover.process(&mut samples);
+
+    let (left, right) = channel_peaks(&samples);
+    assert!(left < 0.005);
+    assert!(right < 0.005);
+}
+
+#[test]
+fn sides_are_kept() {
+    let mut vocal_remover = enabled_vocal_remover();
+    let mut samples = stereo_sine(0.5, 0.0, 2000.0, 48000);
+    vocal_remover.process(&mut samples);
+
+    let (left, right) = channel_peaks(&samples);
+    assert!((left - 0.25).abs() < 0.01);
+    assert!((right - 0.25).abs() < 0.01);
+}
+
+#[test]
+fn bass_is_kept() {
+    let mut vocal_remover = enabled_vocal_remover();
+    let mut samples = stereo_sine(0.5, 0.5, 30.0, 48000);
+    vocal_remover.process(&mut samples);
+
+    let (left, _) = channel_peaks(&samples);
+    assert!(left > 0.45);
+}
+
+#[test]
+fn mono_sources_are_unchanged() {
+    let mut vocal_remover = enabled_vocal_remover();
+    vocal_remover.set_source_channels(1);
+    let input = stereo_sine(0.5, 0.5, 2000.0, 4800);
+    let mut samples = input.clone();
+    vocal_remover.process(&mut samples);
+
+    assert!(!vocal_remover.is_active());
+    assert_eq!(input, samples);
+}
+
+#[test]
+fn toggling_is_gradual() {
+    let mut vocal_remover = enabled_vocal_remover();
+    let input = stereo_sine(0.5, 0.5, 2000.0, 20);
+    let mut samples = input.clone();
+    vocal_remover.process(&mut samples);
+    // Only a small part of the center has been removed so far
+    assert!(samples[38].abs() < input[38].abs());
+    assert!(samples[38].abs() > input[38].abs() * 0.95);
+
+    vocal_remover.set_enabled(false);
+    let mut samples = vec![0.5; 9600];
+    vocal_remover.process(&mut samples);
+    assert!(!vocal_remover.is_active());
+}
@@ -1,8 +1,19 @@
 #[cfg(all(feature = "decoder", feature = "output"))]
 mod audio_manager;
+#[cfg(all(feature = "decoder", feature = "output"))]
+mod crossfade;
 #[cfg(feature = "decoder")]
 pub mod decoder;
+pub mod effects;
+#[cfg(all(feature = "decoder", feature = "output"))]
+mod mixer;
 #[cfg(feature = "output")]
 pub mod output;
 #[cfg(all(feature = "decoder", feature = "output"))]
 pub use audio_manager::*;
+#[cfg(all(feature = "decoder", feature = "output"))]
+pub use crossfade::*;
+#[cfg(all(feature = "decoder", feature = "output"))]
+pub use mixer::*;
+#[cfg(all(test, feature = "output", feature = "mock"))]
+mod test_util;
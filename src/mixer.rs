@@ -0,0 +1,303 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use cpal::{SizedSample, SupportedStreamConfig};
+use dasp::sample::Sample as DaspSample;
+use symphonia::core::audio::conv::ConvertibleSample;
+use symphonia::core::audio::sample::Sample;
+use tracing::warn;
+
+use crate::audio_manager::create_dither;
+use crate::decoder::{
+    DEFAULT_VOLUME_RAMP, Decoder, DecoderError, DecoderResult, DecoderSettings, ResampledDecoder,
+    ResamplerSettings, Source, VolumeRamp, db_to_linear,
+};
+use crate::effects::{Dither, DitherSettings, Effect};
+use crate::output::{
+    AudioBackend, AudioOutput, AudioOutputError, OutputBuilder, WriteBlockingError,
+};
+
+// When the mix goes over full scale, samples above this level are gradually compressed so it
+// stays within range
+const SOFT_CLIP_THRESHOLD: f32 = 0.8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VoiceId(u64);
+
+#[derive(Clone, Debug)]
+pub struct MixerSettings {
+    /// Attenuation applied to the sum of all voices so several loud voices don't clip.
+    pub headroom_db: f32,
+    /// Number of frames mixed for each write.
+    pub chunk_frames: usize,
+    pub resampler_settings: ResamplerSettings,
+    pub volume_ramp: Duration,
+    /// Dithering applied when the mix is converted to a 16-bit or smaller integer format.
+    pub dither: Option<DitherSettings>,
+}
+
+impl Default for MixerSettings {
+    fn default() -> Self {
+        Self {
+            headroom_db: 0.0,
+            chunk_frames: 1024,
+            resampler_settings: ResamplerSettings::default(),
+            volume_ramp: DEFAULT_VOLUME_RAMP,
+            dither: Some(DitherSettings::default()),
+        }
+    }
+}
+
+struct Voice {
+    id: VoiceId,
+    decoder: Decoder<f32>,
+    resampled: ResampledDecoder<f32>,
+    pending: VecDeque<f32>,
+    volume: VolumeRamp,
+    pan: f32,
+    is_finished: bool,
+}
+
+impl Voice {
+    // Decodes enough of the voice to cover the requested number of samples
+    fn fill(&mut self, len: usize) -> Result<(), DecoderError> {
+        while self.pending.len() < len && !self.is_finished {
+            self.pending.extend(self.resampled.current(&self.decoder));
+            if self.resampled.decode_next_frame(&mut self.decoder)? == DecoderResult::Finished {
+                self.pending.extend(self.resampled.flush());
+                self.is_finished = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_drained(&self) -> bool {
+        self.is_finished && self.pending.is_empty()
+    }
+}
+
+/// Plays several decoders at once, such as sound effects on top of music. Like
+/// [`AudioManager`](crate::AudioManager), the decoders always produce `f32` samples and `T` is
+/// only the format the mix is written to the output in.
+pub struct Mixer<T: Sample + DaspSample, B: AudioBackend> {
+    output: AudioOutput<T, B>,
+    output_config: SupportedStreamConfig,
+    voices: Vec<Voice>,
+    next_id: u64,
+    settings: MixerSettings,
+    dither: Option<Dither>,
+    mix_buf: Vec<f32>,
+    voice_buf: Vec<f32>,
+    out_buf: Vec<T>,
+}
+
+impl<
+    T: Sample + DaspSample + SizedSample + ConvertibleSample + rubato::Sample + Send,
+    B: AudioBackend,
+> Mixer<T, B>
+{
+    pub fn new(
+        output_builder: &OutputBuilder<B>,
+        settings: MixerSettings,
+    ) -> Result<Self, AudioOutputError> {
+        let output_config = output_builder.default_output_config()?;
+        let output = output_builder.new_output::<T>(None, output_config.clone())?;
+        let dither = create_dither::<T>(settings.dither.as_ref(), &output_config);
+
+        Ok(Self {
+            output,
+            output_config,
+            voices: Vec::new(),
+            next_id: 0,
+            settings,
+            dither,
+            mix_buf: Vec::new(),
+            voice_buf: Vec::new(),
+            out_buf: Vec::new(),
+        })
+    }
+
+    pub fn start(&mut self) -> Result<(), AudioOutputError> {
+        self.output.start()
+    }
+
+    pub fn stop(&mut self) {
+        self.output.stop();
+    }
+
+    pub fn settings(&self) -> &MixerSettings {
+        &self.settings
+    }
+
+    pub fn init_decoder(
+        &self,
+        source: Box<dyn Source>,
+        decoder_settings: DecoderSettings,
+    ) -> Result<Decoder<f32>, DecoderError> {
+        Decoder::<f32>::new(source, 1.0, self.channels(), decoder_settings)
+    }
+
+    pub fn add_voice(&mut self, mut decoder: Decoder<f32>) -> VoiceId {
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+
+        decoder.set_output_channels(self.channels());
+        let mut resampled = ResampledDecoder::new(
+            self.sample_rate(),
+            self.channels(),
+            self.settings.resampler_settings.clone(),
+        );
+        resampled.initialize(&mut decoder);
+        let mut volume = VolumeRamp::new(1.0);
+        volume.set_ramp_time(self.settings.volume_ramp, self.sample_rate());
+
+        self.voices.push(Voice {
+            id,
+            decoder,
+            resampled,
+            pending: VecDeque::new(),
+            volume,
+            pan: 0.0,
+            is_finished: false,
+        });
+        id
+    }
+
+    /// Stops playing the voice and returns its decoder.
+    pub fn remove_voice(&mut self, id: VoiceId) -> Option<Decoder<f32>> {
+        let index = self.voices.iter().position(|v| v.id == id)?;
+        Some(self.voices.remove(index).decoder)
+    }
+
+    /// Returns false once the voice has finished playing or has been removed.
+    pub fn has_voice(&self, id: VoiceId) -> bool {
+        self.voices.iter().any(|v| v.id == id)
+    }
+
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn voice_decoder_mut(&mut self, id: VoiceId) -> Option<&mut Decoder<f32>> {
+        self.voice_mut(id).map(|v| &mut v.decoder)
+    }
+
+    pub fn set_voice_volume(&mut self, id: VoiceId, volume: T::Float) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.volume.set_target(volume.to_sample());
+        }
+    }
+
+    /// Sets the stereo position of the voice from -1 (left) to 1 (right).
+    pub fn set_voice_pan(&mut self, id: VoiceId, pan: f32) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn pause_voice(&mut self, id: VoiceId) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.decoder.pause();
+        }
+    }
+
+    pub fn resume_voice(&mut self, id: VoiceId) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.decoder.resume();
+        }
+    }
+
+    /// Mixes the next chunk from every voice and writes it to the output. Voices are removed
+    /// once they finish playing.
+    pub fn write(&mut self) -> Result<(), WriteBlockingError> {
+        let channels = self.channels();
+        let len = self.settings.chunk_frames * channels;
+        self.mix_buf.clear();
+        self.mix_buf.resize(len, 0.0);
+
+        for voice in &mut self.voices {
+            if let Err(e) = voice.fill(len) {
+                warn!(
+                    "Error decoding voice {:?}: {e:?}. Stopping voice.",
+                    voice.id
+                );
+                voice.is_finished = true;
+            }
+            let voice_len = len.min(voice.pending.len());
+            self.voice_buf.clear();
+            self.voice_buf.extend(voice.pending.drain(..voice_len));
+            voice.volume.apply(&mut self.voice_buf, channels);
+
+            for (mix_frame, voice_frame) in self
+                .mix_buf
+                .chunks_exact_mut(channels)
+                .zip(self.voice_buf.chunks_exact(channels))
+            {
+                for (channel, (mixed, sample)) in mix_frame.iter_mut().zip(voice_frame).enumerate()
+                {
+                    *mixed += sample * pan_gain(voice.pan, channel, channels);
+                }
+            }
+        }
+        self.voices.retain(|v| !v.is_drained());
+
+        let headroom = db_to_linear(-self.settings.headroom_db);
+        for sample in &mut self.mix_buf {
+            *sample *= headroom;
+        }
+        // A mix that stays within full scale is left untouched
+        if self.mix_buf.iter().any(|s| s.abs() > 1.0) {
+            for sample in &mut self.mix_buf {
+                *sample = soft_clip(*sample);
+            }
+        }
+        // The mix is only quantized once, after everything else has been applied
+        if let Some(dither) = &mut self.dither {
+            dither.process(&mut self.mix_buf);
+        }
+        self.out_buf.clear();
+        self.out_buf.extend(
+            self.mix_buf
+                .iter()
+                .map(|s| s.to_sample::<T::Float>().to_sample::<T>()),
+        );
+        self.output.write_blocking(&self.out_buf)
+    }
+
+    fn voice_mut(&mut self, id: VoiceId) -> Option<&mut Voice> {
+        self.voices.iter_mut().find(|v| v.id == id)
+    }
+
+    fn channels(&self) -> usize {
+        self.output_config.channels() as usize
+    }
+
+    fn sample_rate(&self) -> usize {
+        self.output_config.sample_rate().0 as usize
+    }
+}
+
+// Panning only affects the front left and right channels. The center position leaves both at
+// full volume.
+fn pan_gain(pan: f32, channel: usize, channels: usize) -> f32 {
+    match (channel, channels) {
+        (_, 1) => 1.0,
+        (0, _) => (1.0 - pan).min(1.0),
+        (1, _) => (1.0 + pan).min(1.0),
+        _ => 1.0,
+    }
+}
+
+fn soft_clip(sample: f32) -> f32 {
+    let magnitude = sample.abs();
+    if magnitude <= SOFT_CLIP_THRESHOLD {
+        return sample;
+    }
+    let knee = 1.0 - SOFT_CLIP_THRESHOLD;
+    let compressed = SOFT_CLIP_THRESHOLD + knee * ((magnitude - SOFT_CLIP_THRESHOLD) / knee).tanh();
+    compressed.copysign(sample)
+}
+
+#[cfg(test)]
+#[path = "./mixer_test.rs"]
+mod mixer_test;
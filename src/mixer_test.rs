@@ -0,0 +1,180 @@
+use std::path::Path;
+use std::time::Duration;
+
+use cpal::SampleFormat;
+
+use super::{Mixer, MixerSettings, pan_gain, soft_clip};
+use crate::decoder::{Decoder, DecoderSettings, ReadSeekSource, db_to_linear};
+use crate::output::{MockDevice, MockOutput};
+use crate::test_util::{mock_device, output_builder};
+
+// Each write mixes 1024 frames, which is two device callbacks
+const CHUNK_SAMPLES: usize = 2048;
+
+fn mixer(device: &MockDevice) -> Mixer<f32, MockOutput> {
+    Mixer::new(&output_builder(device.clone()), MixerSettings {
+        // Volume changes take effect straight away
+        volume_ramp: Duration::ZERO,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn open(mixer: &Mixer<f32, MockOutput>, path: &str) -> Decoder<f32> {
+    let source = ReadSeekSource::from_path(Path::new(path));
+    mixer
+        .init_decoder(Box::new(source), DecoderSettings::default())
+        .unwrap()
+}
+
+// The start of the file without any mixing
+fn decode(path: &str, len: usize) -> Vec<f32> {
+    let source = ReadSeekSource::from_path(Path::new(path));
+    let mut decoder = Decoder::new(Box::new(source), 1.0, 2, DecoderSettings::default()).unwrap();
+    let mut samples = decoder.current().to_vec();
+    while samples.len() < len {
+        samples.extend_from_slice(decoder.next().unwrap().unwrap());
+    }
+    samples
+}
+
+// Mixes the given number of chunks and reads them back from the device. Everything fits in the
+// output buffer, so the output is started afterwards.
+fn play(mixer: &mut Mixer<f32, MockOutput>, device: &MockDevice, chunks: usize) -> Vec<f32> {
+    for _ in 0..chunks {
+        mixer.write().unwrap();
+    }
+    mixer.start().unwrap();
+    (0..chunks * 2)
+        .flat_map(|_| device.trigger_callback())
+        .collect()
+}
+
+// The output starts on a chunk boundary. Chunks are only soft-clipped when they go over full scale.
+fn assert_mixed(expected: impl Fn(usize) -> f32, output: &[f32]) {
+    let headroom = db_to_linear(-MixerSettings::default().headroom_db);
+    for (chunk_index, chunk) in output.chunks(CHUNK_SAMPLES).enumerate() {
+        let start = chunk_index * CHUNK_SAMPLES;
+        let mixed: Vec<f32> = (start..start + chunk.len())
+            .map(|i| expected(i) * headroom)
+            .collect();
+        let is_clipped = mixed.iter().any(|s| s.abs() > 1.0);
+        for (sample, mixed) in chunk.iter().zip(mixed) {
+            let mixed = if is_clipped { soft_clip(mixed) } else { mixed };
+            assert!((sample - mixed).abs() < 1e-6);
+        }
+    }
+}
+
+#[test]
+fn voices_are_summed() {
+    let device = mock_device(SampleFormat::F32);
+    let mut mixer = mixer(&device);
+    mixer.add_voice(open(&mixer, "examples/music-1.mp3"));
+    mixer.add_voice(open(&mixer, "examples/music-2.mp3"));
+
+    let output = play(&mut mixer, &device, 4);
+    let first = decode("examples/music-1.mp3", output.len());
+    let second = decode("examples/music-2.mp3", output.len());
+    assert_mixed(|i| first[i] + second[i], &output);
+}
+
+#[test]
+fn voice_volume_and_pan() {
+    let device = mock_device(SampleFormat::F32);
+    let mut mixer = mixer(&device);
+    let quiet = mixer.add_voice(open(&mixer, "examples/music-1.mp3"));
+    let left = mixer.add_voice(open(&mixer, "examples/music-2.mp3"));
+    mixer.set_voice_volume(quiet, 0.5);
+    mixer.set_voice_pan(left, -1.0);
+
+    let output = play(&mut mixer, &device, 4);
+    let first = decode("examples/music-1.mp3", output.len());
+    let second = decode("examples/music-2.mp3", output.len());
+    assert_mixed(
+        |i| {
+            let right_gain = if i % 2 == 1 { 0.0 } else { 1.0 };
+            first[i] * 0.5 + second[i] * right_gain
+        },
+        &output,
+    );
+}
+
+#[test]
+fn voices_finish_independently() {
+    let device = mock_device(SampleFormat::F32);
+    let mut mixer = mixer(&device);
+    let mut short = open(&mixer, "examples/music-1.mp3");
+    let duration = short.duration().unwrap().duration;
+    short.seek(duration - Duration::from_millis(50)).unwrap();
+    let short = mixer.add_voice(short);
+    let long = mixer.add_voice(open(&mixer, "examples/music-2.mp3"));
+
+    let output = play(&mut mixer, &device, 8);
+    assert!(!mixer.has_voice(short));
+    assert!(mixer.has_voice(long));
+    assert_eq!(1, mixer.voice_count());
+
+    // Once the short voice has finished, only the long one is heard
+    let last_chunk = output.len() - CHUNK_SAMPLES;
+    let long_samples = decode("examples/music-2.mp3", output.len());
+    assert_mixed(|i| long_samples[last_chunk + i], &output[last_chunk..]);
+}
+
+#[test]
+fn dither_16_bit_device() {
+    let device = mock_device(SampleFormat::I16);
+    let mut mixer = mixer(&device);
+    let mut decoder = open(&mixer, "examples/music-1.mp3");
+    decoder.seek(Duration::from_secs(2)).unwrap();
+    // Quieter than the smallest 16-bit step
+    let voice = mixer.add_voice(decoder);
+    mixer.set_voice_volume(voice, 0.00001);
+
+    let output = play(&mut mixer, &device, 4);
+    assert!(output.iter().any(|s| *s != 0.0));
+    assert!(output.iter().all(|s| s.abs() <= 2.0 / 32768.0));
+}
+
+#[test]
+fn loud_voices_stay_within_full_scale() {
+    let device = mock_device(SampleFormat::F32);
+    let mut mixer = mixer(&device);
+    for _ in 0..4 {
+        let voice = mixer.add_voice(open(&mixer, "examples/music-1.mp3"));
+        mixer.set_voice_volume(voice, 4.0);
+    }
+
+    let output = play(&mut mixer, &device, 4);
+    assert!(output.iter().all(|s| s.abs() <= 1.0));
+    assert!(output.iter().any(|s| s.abs() > 0.9));
+}
+
+#[test]
+fn soft_clip_limits_to_full_scale() {
+    assert_eq!(0.5, soft_clip(0.5));
+    assert_eq!(-0.8, soft_clip(-0.8));
+    assert!(soft_clip(1.0) < 1.0);
+    assert!(soft_clip(1.0) > soft_clip(0.9));
+    assert!(soft_clip(10.0) <= 1.0);
+    assert!(soft_clip(-10.0) >= -1.0);
+}
+
+#[test]
+fn pan_center() {
+    assert_eq!(1.0, pan_gain(0.0, 0, 2));
+    assert_eq!(1.0, pan_gain(0.0, 1, 2));
+}
+
+#[test]
+fn pan_hard_left() {
+    assert_eq!(1.0, pan_gain(-1.0, 0, 2));
+    assert_eq!(0.0, pan_gain(-1.0, 1, 2));
+    // Surround channels aren't affected
+    assert_eq!(1.0, pan_gain(-1.0, 4, 6));
+}
+
+#[test]
+fn pan_mono_output() {
+    assert_eq!(1.0, pan_gain(1.0, 0, 1));
+}
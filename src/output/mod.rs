@@ -3,7 +3,7 @@ use std::time::Duration;
 
 use cpal::{
     BackendSpecificError, BuildStreamError, ChannelCount, DefaultStreamConfigError,
-    DeviceNameError, DevicesError, HostId, HostUnavailable, PlayStreamError, SampleFormat,
+    DeviceNameError, DevicesError, HostId, HostUnavailable, PlayStreamError, Sample, SampleFormat,
     SampleRate, SizedSample, StreamConfig, StreamError, SupportedStreamConfig,
     SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
@@ -277,6 +277,20 @@ impl<B: AudioBackend> OutputBuilder<B> {
             return Ok(matched_config.with_sample_rate(sample_rate));
         }
 
+        // Fall back to the closest channel count, preferring configs with more channels so
+        // nothing needs to be discarded
+        if let Some(matched_config) = device
+            .supported_output_configs()?
+            .filter(|c| {
+                c.sample_format() == sample_format
+                    && c.min_sample_rate() <= sample_rate
+                    && c.max_sample_rate() >= sample_rate
+            })
+            .min_by_key(|c| (c.channels() < channels, c.channels().abs_diff(channels)))
+        {
+            return Ok(matched_config.with_sample_rate(sample_rate));
+        }
+
         Ok(default_config)
     }
 
@@ -430,6 +444,58 @@ impl<T: SizedSample + Default + Send + 'static, B: AudioBackend> AudioOutput<T,
     fn create_stream(
         &self,
         ring_buf_consumer: rb::Consumer<T>,
+    ) -> Result<B::Stream, AudioOutputError> {
+        let format = self.config.sample_format();
+        if format == T::FORMAT {
+            return self
+                .build_stream(move |data: &mut [T]| ring_buf_consumer.read(data).unwrap_or(0));
+        }
+        // Samples are converted in the callback if the device uses a different format
+        match format {
+            SampleFormat::I8 => self.build_stream(self.read_converted::<i8>(ring_buf_consumer)),
+            SampleFormat::I16 => self.build_stream(self.read_converted::<i16>(ring_buf_consumer)),
+            SampleFormat::I32 => self.build_stream(self.read_converted::<i32>(ring_buf_consumer)),
+            SampleFormat::I64 => self.build_stream(self.read_converted::<i64>(ring_buf_consumer)),
+            SampleFormat::U8 => self.build_stream(self.read_converted::<u8>(ring_buf_consumer)),
+            SampleFormat::U16 => self.build_stream(self.read_converted::<u16>(ring_buf_consumer)),
+            SampleFormat::U32 => self.build_stream(self.read_converted::<u32>(ring_buf_consumer)),
+            SampleFormat::U64 => self.build_stream(self.read_converted::<u64>(ring_buf_consumer)),
+            SampleFormat::F32 => self.build_stream(self.read_converted::<f32>(ring_buf_consumer)),
+            SampleFormat::F64 => self.build_stream(self.read_converted::<f64>(ring_buf_consumer)),
+            format => Err(AudioOutputError::UnsupportedConfiguration(format!(
+                "sample format {format}"
+            ))),
+        }
+    }
+
+    // Reads from the ring buffer into a scratch buffer before converting. The scratch buffer is
+    // allocated here since allocating in the callback could cause an underrun.
+    fn read_converted<S: SizedSample + Send + 'static>(
+        &self,
+        ring_buf_consumer: rb::Consumer<T>,
+    ) -> impl FnMut(&mut [S]) -> usize + Send + 'static {
+        let mut buf = vec![T::EQUILIBRIUM; self.ring_buf.capacity()];
+        move |data: &mut [S]| {
+            let mut written = 0;
+            for chunk in data.chunks_mut(buf.len()) {
+                let read = ring_buf_consumer.read(&mut buf[..chunk.len()]).unwrap_or(0);
+                for (out, sample) in chunk.iter_mut().zip(&buf[..read]) {
+                    *out = convert(*sample);
+                }
+                written += read;
+                if read < chunk.len() {
+                    break;
+                }
+            }
+            written
+        }
+    }
+
+    // The callback fills as much of the device's buffer as it can and returns the number of
+    // samples written
+    fn build_stream<S: SizedSample + Send + 'static>(
+        &self,
+        mut fill: impl FnMut(&mut [S]) -> usize + Send + 'static,
     ) -> Result<B::Stream, AudioOutputError> {
         let channels = self.config.channels();
         let config = StreamConfig {
@@ -440,15 +506,15 @@ impl<T: SizedSample + Default + Send + 'static, B: AudioBackend> AudioOutput<T,
         info!("Output channels = {channels}");
         info!("Output sample rate = {}", self.config.sample_rate().0);
 
-        let filler = T::EQUILIBRIUM;
+        let filler = S::EQUILIBRIUM;
         let on_error = self.on_error.clone();
         let on_device_changed = self.on_device_changed.clone();
         let stream = self.device.build_output_stream(
             &config,
-            move |data: &mut [T]| {
+            move |data: &mut [S]| {
                 // Write out as many samples as possible from the ring buffer to the audio
                 // output.
-                let written = ring_buf_consumer.read(data).unwrap_or(0);
+                let written = fill(data);
                 // Mute any remaining samples.
                 if data.len() > written {
                     warn!("Output buffer not full, muting remaining",);
@@ -473,6 +539,14 @@ impl<T: SizedSample + Default + Send + 'static, B: AudioBackend> AudioOutput<T,
     }
 }
 
+fn convert<T: Sample, S: Sample>(sample: T) -> S {
+    sample
+        .to_float_sample()
+        .to_sample::<f64>()
+        .to_sample::<S::Float>()
+        .to_sample()
+}
+
 #[cfg(test)]
 #[path = "./output_config_test.rs"]
 mod output_config_test;
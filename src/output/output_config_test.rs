@@ -1,6 +1,8 @@
 use std::vec;
 
-use cpal::{SampleFormat, SampleRate, SupportedBufferSize, SupportedStreamConfig};
+use cpal::{
+    SampleFormat, SampleRate, SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
+};
 
 use super::{MockDevice, MockHost, MockOutput, OutputBuilder, RequestedOutputConfig};
 
@@ -241,3 +243,55 @@ fn find_closest_config_non_default_device() {
     assert_eq!(SampleFormat::F32, config.sample_format());
     assert_eq!(SampleRate(48000), config.sample_rate());
 }
+
+#[test]
+fn find_closest_config_closest_channels() {
+    let output_builder = OutputBuilder::new(
+        MockOutput {
+            default_host: MockHost {
+                default_device: MockDevice::new(
+                    "test-device".to_owned(),
+                    SupportedStreamConfig::new(
+                        2,
+                        SampleRate(44100),
+                        SupportedBufferSize::Range { min: 0, max: 9999 },
+                        SampleFormat::F32,
+                    ),
+                    SampleRate(1024),
+                    SampleRate(192000),
+                    vec![
+                        SupportedStreamConfigRange::new(
+                            4,
+                            SampleRate(1024),
+                            SampleRate(192000),
+                            SupportedBufferSize::Range { min: 0, max: 9999 },
+                            SampleFormat::F32,
+                        ),
+                        SupportedStreamConfigRange::new(
+                            8,
+                            SampleRate(1024),
+                            SampleRate(192000),
+                            SupportedBufferSize::Range { min: 0, max: 9999 },
+                            SampleFormat::F32,
+                        ),
+                    ],
+                ),
+                additional_devices: vec![],
+            },
+        },
+        Default::default(),
+        move || {},
+        |_| {},
+    );
+    let config = output_builder
+        .find_closest_config(None, RequestedOutputConfig {
+            sample_rate: Some(SampleRate(48000)),
+            channels: Some(6),
+            sample_format: None,
+        })
+        .unwrap();
+
+    assert_eq!(8, config.channels());
+    assert_eq!(SampleFormat::F32, config.sample_format());
+    assert_eq!(SampleRate(48000), config.sample_rate());
+}
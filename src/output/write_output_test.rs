@@ -38,3 +38,39 @@ fn test_write_output() {
     let written = output.device().trigger_callback();
     assert_eq!([1.0; 1024], written);
 }
+
+#[test]
+fn converts_to_device_format() {
+    let output_builder = OutputBuilder::new(
+        MockOutput {
+            default_host: MockHost {
+                default_device: MockDevice::new(
+                    "test-device".to_owned(),
+                    SupportedStreamConfig::new(
+                        2,
+                        SampleRate(44100),
+                        SupportedBufferSize::Range { min: 0, max: 9999 },
+                        SampleFormat::I16,
+                    ),
+                    SampleRate(1024),
+                    SampleRate(192000),
+                    vec![],
+                ),
+                additional_devices: vec![],
+            },
+        },
+        Default::default(),
+        move || {},
+        |_| {},
+    );
+
+    let mut output = output_builder
+        .new_output::<f32>(None, output_builder.default_output_config().unwrap())
+        .unwrap();
+
+    output.start().unwrap();
+    // Both values are exact 16-bit steps, so they come back unchanged
+    output.write_blocking(&[0.5, -0.25].repeat(512)).unwrap();
+    let written = output.device().trigger_callback();
+    assert_eq!([0.5, -0.25].repeat(512), written);
+}
@@ -0,0 +1,33 @@
+use cpal::{SampleFormat, SampleRate, SupportedBufferSize, SupportedStreamConfig};
+
+use crate::output::{MockDevice, MockHost, MockOutput, OutputBuilder};
+
+// A stereo 44.1 kHz device that is the only one on the default host
+pub(crate) fn mock_device(sample_format: SampleFormat) -> MockDevice {
+    MockDevice::new(
+        "test-device".to_owned(),
+        SupportedStreamConfig::new(
+            2,
+            SampleRate(44100),
+            SupportedBufferSize::Range { min: 0, max: 9999 },
+            sample_format,
+        ),
+        SampleRate(1024),
+        SampleRate(192000),
+        vec![],
+    )
+}
+
+pub(crate) fn output_builder(device: MockDevice) -> OutputBuilder<MockOutput> {
+    OutputBuilder::new(
+        MockOutput {
+            default_host: MockHost {
+                default_device: device,
+                additional_devices: vec![],
+            },
+        },
+        Default::default(),
+        move || {},
+        |_| {},
+    )
+}